@@ -1,4 +1,6 @@
-use rbx_mantle::config::Config;
+use std::env;
+
+use rbx_mantle::config::{Config, ExperienceTargetConfig};
 use schemars::{
     gen::SchemaSettings,
     schema::SchemaObject,
@@ -44,14 +46,24 @@ impl Visitor for MarkdownVisitor {
 }
 
 fn main() {
-    let schema = SchemaSettings::draft07()
+    let generator = SchemaSettings::draft07()
         .with_visitor(MarkdownVisitor)
         .with(|s| {
             s.inline_subschemas = true;
             s.option_add_null_type = false;
         })
-        .into_generator()
-        .into_root_schema_for::<Config>();
+        .into_generator();
+
+    // The `include` schema describes the files which can be merged into a config with its
+    // `include` property.
+    let schema = match env::args().nth(1).as_deref() {
+        None | Some("config") => generator.into_root_schema_for::<Config>(),
+        Some("include") => generator.into_root_schema_for::<ExperienceTargetConfig>(),
+        Some(other) => panic!(
+            "Unknown schema: {}. Expected one of: config, include",
+            other
+        ),
+    };
 
     #[cfg(debug_assertions)]
     println!("{}", serde_json::to_string_pretty(&schema).unwrap());
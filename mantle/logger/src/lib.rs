@@ -1,6 +1,8 @@
 use std::{
+    cell::RefCell,
     fmt::Display,
-    panic,
+    future::Future,
+    mem, panic,
     sync::atomic::{AtomicU16, Ordering},
 };
 
@@ -11,6 +13,49 @@ const SPACING: &str = "  ";
 
 static ACTION_COUNT: AtomicU16 = AtomicU16::new(0);
 
+tokio::task_local! {
+    // The logs of a task which is run at the same time as other tasks
+    static CAPTURED_LOGS: RefCell<CapturedLogs>;
+}
+
+#[derive(Default)]
+struct CapturedLogs {
+    action_count: u16,
+    logs: String,
+}
+
+/// Buffers the logs of the future instead of printing them so that several futures can run at the
+/// same time without interleaving their logs. The future resolves to its output and its logs.
+pub async fn capture<F: Future>(future: F) -> (F::Output, String) {
+    CAPTURED_LOGS
+        .scope(RefCell::new(CapturedLogs::default()), async move {
+            let output = future.await;
+            let logs = CAPTURED_LOGS.with(|captured| mem::take(&mut captured.borrow_mut().logs));
+            (output, logs)
+        })
+        .await
+}
+
+fn with_captured_logs<F, R>(f: F) -> Option<R>
+where
+    F: FnOnce(&mut CapturedLogs) -> R,
+{
+    CAPTURED_LOGS
+        .try_with(|captured| f(&mut captured.borrow_mut()))
+        .ok()
+}
+
+fn get_action_count() -> u16 {
+    with_captured_logs(|captured_logs| captured_logs.action_count)
+        .unwrap_or_else(|| ACTION_COUNT.load(Ordering::SeqCst))
+}
+
+fn set_action_count(action_count: u16) {
+    if with_captured_logs(|captured_logs| captured_logs.action_count = action_count).is_none() {
+        ACTION_COUNT.store(action_count, Ordering::SeqCst);
+    }
+}
+
 fn with_prefix_and_style<S1, S2>(text: S1, prefix: S2, style: Style) -> String
 where
     S1: Display,
@@ -32,15 +77,22 @@ where
 }
 
 fn get_line_prefix() -> String {
-    format!("{SPACING}│{SPACING}").repeat(ACTION_COUNT.load(Ordering::SeqCst).into())
+    format!("{SPACING}│{SPACING}").repeat(get_action_count().into())
 }
 
 pub fn log<S>(message: S)
 where
     S: Display,
 {
-    let line_prefix = get_line_prefix();
-    eprintln!("{}", with_prefix(&message, line_prefix));
+    let line = with_prefix(&message, get_line_prefix());
+    let is_captured = with_captured_logs(|captured_logs| {
+        captured_logs.logs.push_str(&line);
+        captured_logs.logs.push('\n');
+    })
+    .is_some();
+    if !is_captured {
+        eprintln!("{}", line);
+    }
 }
 
 pub fn start_action<S>(title: S)
@@ -49,19 +101,20 @@ where
 {
     log(title);
     log("  ╷");
-    ACTION_COUNT.fetch_add(1, Ordering::SeqCst);
+    set_action_count(get_action_count() + 1);
 }
 
 fn end_action_internal<S>(message: Option<S>, results: Option<Changeset>)
 where
     S: Display,
 {
-    if ACTION_COUNT.load(Ordering::SeqCst) == 0 {
+    let action_count = get_action_count();
+    if action_count == 0 {
         panic!("Attempted to end an action that was not started.");
     }
 
     log("");
-    ACTION_COUNT.fetch_sub(1, Ordering::SeqCst);
+    set_action_count(action_count - 1);
 
     if let Some(message) = message {
         log(format!("{SPACING}╰─ {message}"));
@@ -0,0 +1,118 @@
+use std::io::{self, IsTerminal, Write};
+
+use yansi::Paint;
+
+use rbx_mantle::{
+    config::EnvironmentConfig,
+    project::ProjectTarget,
+    resource_graph::{ResourceGraph, ResourceGraphDiff},
+    roblox_resource_manager::{RobloxInputs, RobloxOutputs, RobloxResource},
+};
+
+use crate::commands::diff::print_diff;
+
+fn log_experiences(
+    targets: &[ProjectTarget],
+    graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+) {
+    for target in targets {
+        let experience = match graph.get_outputs(&target.resource_id("experience_singleton")) {
+            Some(RobloxOutputs::Experience(outputs)) => Paint::cyan(outputs.asset_id).to_string(),
+            _ => "not yet created".to_owned(),
+        };
+        match &target.label {
+            Some(label) => logger::log(format!(
+                "Experience ({}): {}",
+                Paint::cyan(label),
+                experience
+            )),
+            None => logger::log(format!("Experience: {}", experience)),
+        }
+    }
+}
+
+fn prompt(message: &str) -> Result<String, String> {
+    eprint!("{}", message);
+    io::stderr()
+        .flush()
+        .map_err(|e| format!("Unable to prompt for confirmation: {}", e))?;
+
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .map_err(|e| format!("Unable to read confirmation: {}", e))?;
+    Ok(answer.trim().to_owned())
+}
+
+/// Prints the planned changes to an environment and asks the user to approve them. Returns
+/// whether the changes were approved.
+///
+/// Changes are approved without asking when `yes` is set, or when not running interactively
+/// unless the environment is `protected`. Destroying or changing a protected environment must be
+/// confirmed by typing the environment's label.
+pub fn approve_changes(
+    action: &str,
+    environment_config: &EnvironmentConfig,
+    targets: &[ProjectTarget],
+    current_graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+    diff: &ResourceGraphDiff,
+    yes: bool,
+) -> Result<bool, String> {
+    logger::start_action("Planned changes:");
+    logger::log(format!(
+        "Environment: {}",
+        Paint::cyan(&environment_config.label)
+    ));
+    log_experiences(targets, current_graph);
+    logger::log("");
+    print_diff(diff);
+
+    let change_count = diff.additions.len()
+        + diff.changes.len()
+        + diff.removals.len()
+        + diff.dependency_changes.len();
+    if change_count == 0 {
+        logger::end_action("No changes required");
+        return Ok(true);
+    }
+    logger::end_action(format!(
+        "{} addition(s), {} change(s), {} dependency change(s), {} removal(s)",
+        diff.additions.len(),
+        diff.changes.len(),
+        diff.dependency_changes.len(),
+        diff.removals.len()
+    ));
+
+    if yes {
+        return Ok(true);
+    }
+
+    if !io::stdin().is_terminal() {
+        if environment_config.protected {
+            return Err(format!(
+                "Environment {} is protected: pass --yes to {} it non-interactively",
+                environment_config.label, action
+            ));
+        }
+        return Ok(true);
+    }
+
+    if environment_config.protected || action == "destroy" {
+        let answer = prompt(&format!(
+            "Type the environment label ({}) to {} it: ",
+            environment_config.label, action
+        ))?;
+        Ok(answer == environment_config.label)
+    } else {
+        let mut action_chars = action.chars();
+        let action = action_chars
+            .next()
+            .map(|c| c.to_uppercase().chain(action_chars).collect::<String>())
+            .unwrap_or_default();
+        let answer = prompt(&format!(
+            "{} to environment {}? [y/N] ",
+            action, environment_config.label
+        ))?;
+        Ok(matches!(answer.to_lowercase().as_str(), "y" | "yes"))
+    }
+}
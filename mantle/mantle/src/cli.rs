@@ -1,5 +1,5 @@
-use crate::commands;
-use clap::{crate_version, App, AppSettings, Arg, SubCommand};
+use crate::{commands, report, workspace};
+use clap::{crate_version, App, AppSettings, Arg, ArgMatches, SubCommand};
 use std::env;
 
 fn get_app() -> App<'static, 'static> {
@@ -7,14 +7,36 @@ fn get_app() -> App<'static, 'static> {
         .version(crate_version!())
         .about("Infra-as-code and deployment tool for Roblox")
         .setting(AppSettings::ArgRequiredElseHelp)
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .help("The format of the command's results. With `json`, a single JSON document describing the command's results is printed to stdout once it completes. Defaults to `text`.")
+                .value_name("FORMAT")
+                .takes_value(true)
+                .possible_values(&["text", "json"])
+                .global(true))
         .subcommand(
             SubCommand::with_name("deploy")
                 .about("Updates a Mantle environment with a project's latest configuration.")
                 .arg(
                     Arg::with_name("PROJECT")
                         .index(1)
-                        .help("The Mantle project: either the path to a directory containing a 'mantle.yml' file or the path to a configuration file. Defaults to the current directory.")
+                        .help("The Mantle project: either the path to a directory containing a 'mantle.yml' file or the path to a configuration file. Defaults to the current directory. Multiple projects can be provided to run the command for each of them.")
+                        .takes_value(true)
+                        .multiple(true))
+                .arg(
+                    Arg::with_name("workspace")
+                        .long("workspace")
+                        .help("A workspace file listing the projects to run the command for.")
+                        .value_name("FILE")
                         .takes_value(true))
+                .arg(
+                    Arg::with_name("parallel")
+                        .long("parallel")
+                        .help("The number of projects to deploy at the same time when deploying multiple projects.")
+                        .value_name("JOBS")
+                        .takes_value(true)
+                        .requires("yes"))
                 .arg(
                     Arg::with_name("environment")
                         .long("environment")
@@ -22,10 +44,57 @@ fn get_app() -> App<'static, 'static> {
                         .help("The label of the environment to deploy to. If not specified, attempts to match the current git branch to each environment's `branches` property.")
                         .value_name("ENVIRONMENT")
                         .takes_value(true))
+                .arg(
+                    Arg::with_name("target_label")
+                        .long("target-label")
+                        .help("The label of the target to deploy when the project has multiple `targets`. If not specified, all targets are deployed.")
+                        .value_name("LABEL")
+                        .takes_value(true))
                 .arg(
                     Arg::with_name("allow_purchases")
                         .long("allow-purchases")
                         .help("Gives Mantle permission to make purchases with Robux."))
+                .arg(
+                    Arg::with_name("yes")
+                        .long("yes")
+                        .short("y")
+                        .help("Applies the planned changes without asking for confirmation. Required to deploy to a `protected` environment non-interactively."))
+                .arg(
+                    Arg::with_name("report")
+                        .long("report")
+                        .help("Writes a Markdown report of the deployment to the file, e.g. for a pull request comment.")
+                        .value_name("FILE")
+                        .takes_value(true))
+                .arg(
+                    Arg::with_name("step_summary")
+                        .long("step-summary")
+                        .help("Appends a Markdown report of the deployment to the GitHub Actions job summary (`$GITHUB_STEP_SUMMARY`)."))
+                .arg(
+                    Arg::with_name("no_push")
+                        .long("no-push")
+                        .help("Creates the tags for an environment with `tagCommit` enabled without pushing them to the remote."))
+                .arg(
+                    Arg::with_name("watch")
+                        .long("watch")
+                        .help("Keeps running after deploying and deploys again whenever the project's config files, place files or assets change."))
+                .arg(
+                    Arg::with_name("debounce")
+                        .long("debounce")
+                        .help("The number of milliseconds to wait for files to stop changing before deploying in watch mode.")
+                        .value_name("MILLISECONDS")
+                        .takes_value(true)
+                        .default_value("500"))
+                .arg(
+                    Arg::with_name("timings")
+                        .long("timings")
+                        .help("Prints how long each phase of the deployment and each resource took once the deployment completes."))
+                .arg(
+                    Arg::with_name("slow_threshold")
+                        .long("slow-threshold")
+                        .help("The number of milliseconds after which a resource is highlighted as slow in the timings.")
+                        .value_name("MILLISECONDS")
+                        .takes_value(true)
+                        .default_value("5000"))
         )
          .subcommand(
             SubCommand::with_name("diff")
@@ -33,7 +102,20 @@ fn get_app() -> App<'static, 'static> {
                 .arg(
                     Arg::with_name("PROJECT")
                         .index(1)
-                        .help("The Mantle project: either the path to a directory containing a 'mantle.yml' file or the path to a configuration file. Defaults to the current directory.")
+                        .help("The Mantle project: either the path to a directory containing a 'mantle.yml' file or the path to a configuration file. Defaults to the current directory. Multiple projects can be provided to run the command for each of them.")
+                        .takes_value(true)
+                        .multiple(true))
+                .arg(
+                    Arg::with_name("workspace")
+                        .long("workspace")
+                        .help("A workspace file listing the projects to run the command for.")
+                        .value_name("FILE")
+                        .takes_value(true))
+                .arg(
+                    Arg::with_name("parallel")
+                        .long("parallel")
+                        .help("The number of projects to diff at the same time when diffing multiple projects.")
+                        .value_name("JOBS")
                         .takes_value(true))
                 .arg(
                     Arg::with_name("environment")
@@ -43,8 +125,29 @@ fn get_app() -> App<'static, 'static> {
                         .value_name("ENVIRONMENT")
                         .takes_value(true))
                 .arg(
-                    Arg::with_name("output")
-                        .long("output")
+                    Arg::with_name("target_label")
+                        .long("target-label")
+                        .help("The label of the target to diff when the project has multiple `targets`. If not specified, all targets are diffed.")
+                        .value_name("LABEL")
+                        .takes_value(true))
+                .arg(
+                    Arg::with_name("from_env")
+                        .long("from-env")
+                        .help("Compares the stored state of this environment with the environment provided by `--to-env` instead of diffing the configuration. The diff shows the changes which would make the `--to-env` environment match this one.")
+                        .value_name("ENVIRONMENT")
+                        .takes_value(true)
+                        .requires("to_env")
+                        .conflicts_with_all(&["environment", "target_label"]))
+                .arg(
+                    Arg::with_name("to_env")
+                        .long("to-env")
+                        .help("The environment to compare the `--from-env` environment with.")
+                        .value_name("ENVIRONMENT")
+                        .takes_value(true)
+                        .requires("from_env"))
+                .arg(
+                    Arg::with_name("output_file")
+                        .long("output-file")
                         .short("o")
                         .help("A file path to print the diff to, if a format is provided")
                         .value_name("FILE")
@@ -57,6 +160,59 @@ fn get_app() -> App<'static, 'static> {
                         .value_name("FORMAT")
                         .takes_value(true)
                         .possible_values(&["json","yaml"]))
+                .arg(
+                    Arg::with_name("report")
+                        .long("report")
+                        .help("Writes a Markdown report of the changes to the file, e.g. for a pull request comment.")
+                        .value_name("FILE")
+                        .takes_value(true))
+                .arg(
+                    Arg::with_name("step_summary")
+                        .long("step-summary")
+                        .help("Appends a Markdown report of the changes to the GitHub Actions job summary (`$GITHUB_STEP_SUMMARY`)."))
+        )
+        .subcommand(
+            SubCommand::with_name("validate")
+                .about("Validates a project's configuration for every environment without making any changes.")
+                .arg(
+                    Arg::with_name("PROJECT")
+                        .index(1)
+                        .help("The Mantle project: either the path to a directory containing a 'mantle.yml' file or the path to a configuration file. Defaults to the current directory.")
+                        .takes_value(true))
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .short("f")
+                        .help("The format to print the validation results in")
+                        .value_name("FORMAT")
+                        .takes_value(true)
+                        .possible_values(&["json"]))
+        )
+        .subcommand(
+            SubCommand::with_name("promote")
+                .about("Publishes the place files deployed to one environment to another environment.")
+                .arg(
+                    Arg::with_name("FROM_ENVIRONMENT")
+                        .index(1)
+                        .help("The label of the environment to promote the place files of.")
+                        .required(true)
+                        .takes_value(true))
+                .arg(
+                    Arg::with_name("TO_ENVIRONMENT")
+                        .index(2)
+                        .help("The label of the environment to publish the place files to.")
+                        .required(true)
+                        .takes_value(true))
+                .arg(
+                    Arg::with_name("PROJECT")
+                        .index(3)
+                        .help("The Mantle project: either the path to a directory containing a 'mantle.yml' file or the path to a configuration file. Defaults to the current directory.")
+                        .takes_value(true))
+                .arg(
+                    Arg::with_name("yes")
+                        .long("yes")
+                        .short("y")
+                        .help("Promotes the place files without asking for confirmation. Required to promote to a `protected` environment non-interactively."))
         )
         .subcommand(
             SubCommand::with_name("destroy")
@@ -73,6 +229,66 @@ fn get_app() -> App<'static, 'static> {
                         .help("The label of the environment to destroy. If not specified, attempts to match the current git branch to each environment's `branches` property.")
                         .value_name("ENVIRONMENT")
                         .takes_value(true))
+                .arg(
+                    Arg::with_name("yes")
+                        .long("yes")
+                        .short("y")
+                        .help("Destroys the environment without asking for confirmation. Required to destroy a `protected` environment non-interactively."))
+                .arg(
+                    Arg::with_name("dry_run")
+                        .long("dry-run")
+                        .help("Prints the resources which would be destroyed without destroying them."))
+                .arg(
+                    Arg::with_name("target")
+                        .long("target")
+                        .help("A glob pattern of the IDs of resources to destroy (e.g. `product_*`). Resources which depend on them are also destroyed. Can be specified multiple times. If not specified, all resources are destroyed.")
+                        .value_name("RESOURCE_ID")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1))
+                .arg(
+                    Arg::with_name("exclude")
+                        .long("exclude")
+                        .help("A glob pattern of the IDs of resources to keep. Resources which they depend on are also kept. Can be specified multiple times.")
+                        .value_name("RESOURCE_ID")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1))
+                .arg(
+                    Arg::with_name("keep_experience")
+                        .long("keep-experience")
+                        .help("Keeps the experience, its configuration and its start place, and destroys everything else (e.g. products, passes, badges, assets, notifications and other places)."))
+        )
+        .subcommand(
+            SubCommand::with_name("gc")
+                .about("Destroys ephemeral environments whose branch no longer exists on the remote or which have not been deployed recently.")
+                .arg(
+                    Arg::with_name("PROJECT")
+                        .index(1)
+                        .help("The Mantle project: either the path to a directory containing a 'mantle.yml' file or the path to a configuration file. Defaults to the current directory.")
+                        .takes_value(true))
+                .arg(
+                    Arg::with_name("remote")
+                        .long("remote")
+                        .help("The git remote to check for branches.")
+                        .value_name("REMOTE")
+                        .takes_value(true)
+                        .default_value("origin"))
+                .arg(
+                    Arg::with_name("older_than")
+                        .long("older-than")
+                        .help("Also destroys ephemeral environments which have not been deployed for this many days.")
+                        .value_name("DAYS")
+                        .takes_value(true))
+                .arg(
+                    Arg::with_name("yes")
+                        .long("yes")
+                        .short("y")
+                        .help("Destroys the environments without asking for confirmation."))
+                .arg(
+                    Arg::with_name("dry_run")
+                        .long("dry-run")
+                        .help("Prints the environments which would be destroyed without destroying them."))
         )
         .subcommand(
             SubCommand::with_name("outputs")
@@ -80,7 +296,14 @@ fn get_app() -> App<'static, 'static> {
                 .arg(
                     Arg::with_name("PROJECT")
                         .index(1)
-                        .help("The Mantle project: either the path to a directory containing a 'mantle.yml' file or the path to a configuration file. Defaults to the current directory.")
+                        .help("The Mantle project: either the path to a directory containing a 'mantle.yml' file or the path to a configuration file. Defaults to the current directory. Multiple projects can be provided to run the command for each of them.")
+                        .takes_value(true)
+                        .multiple(true))
+                .arg(
+                    Arg::with_name("workspace")
+                        .long("workspace")
+                        .help("A workspace file listing the projects to run the command for.")
+                        .value_name("FILE")
                         .takes_value(true))
                 .arg(
                     Arg::with_name("environment")
@@ -90,8 +313,8 @@ fn get_app() -> App<'static, 'static> {
                         .value_name("ENVIRONMENT")
                         .takes_value(true))
                 .arg(
-                    Arg::with_name("output")
-                        .long("output")
+                    Arg::with_name("output_file")
+                        .long("output-file")
                         .short("o")
                         .help("A file path to print the outputs to")
                         .value_name("FILE")
@@ -100,20 +323,40 @@ fn get_app() -> App<'static, 'static> {
                     Arg::with_name("format")
                         .long("format")
                         .short("f")
-                        .help("The format to print the outputs in")
+                        .help("The format to print the outputs in. The env and dotenv formats export each output as a variable such as MANTLE_PLACE_START_ASSET_ID. The luau and ts formats generate a module of the IDs of every environment.")
                         .value_name("FORMAT")
                         .takes_value(true)
-                        .possible_values(&["json","yaml"])
+                        .possible_values(&["json","yaml","env","dotenv","luau","ts"])
                         .default_value("json"))
+                .arg(
+                    Arg::with_name("get")
+                        .long("get")
+                        .help("Prints a single output, e.g. `place_start.assetId`.")
+                        .value_name("RESOURCE.FIELD")
+                        .takes_value(true))
+                .arg(
+                    Arg::with_name("labels")
+                        .long("labels")
+                        .help("Groups the outputs by the labels used in the configuration (places, products, passes, badges and assets) rather than by resource ID."))
         )
         .subcommand(
             SubCommand::with_name("import")
-                .about("Imports an existing target into a Mantle environment.")
+                .about("Imports an existing target, or a single existing resource, into a Mantle environment.")
                 .arg(
                     Arg::with_name("PROJECT")
                         .index(1)
                         .help("The Mantle project: either the path to a directory containing a 'mantle.yml' file or the path to a configuration file. Defaults to the current directory.")
                         .takes_value(true))
+                .arg(
+                    Arg::with_name("RESOURCE_ID")
+                        .index(2)
+                        .help("The ID of a single resource to import into the environment's existing state (e.g. `product_gems500`). May be provided without PROJECT.")
+                        .takes_value(true))
+                .arg(
+                    Arg::with_name("ROBLOX_ID")
+                        .index(3)
+                        .help("The Roblox ID of the resource to import.")
+                        .takes_value(true))
                 .arg(
                     Arg::with_name("environment")
                         .long("environment")
@@ -127,7 +370,20 @@ fn get_app() -> App<'static, 'static> {
                         .help("The ID of the target to import.")
                         .value_name("ID")
                         .takes_value(true)
-                        .required(true))
+                        .required_unless("RESOURCE_ID")
+                        .conflicts_with("RESOURCE_ID"))
+                .arg(
+                    Arg::with_name("write_config")
+                        .long("write-config")
+                        .help("Generates the target's configuration from the imported experience and writes it to the provided file (relative to the project), downloading any places and images it references. The file can then be added to the project's `include` list.")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .conflicts_with("RESOURCE_ID"))
+                .arg(
+                    Arg::with_name("reconcile")
+                        .long("reconcile")
+                        .help("Matches the imported resources to the resources in the project's configuration (by name, and by downloading and comparing files) so that unchanged resources are not recreated or updated by the next deployment.")
+                        .conflicts_with_all(&["RESOURCE_ID", "write_config"]))
         )
         .subcommand(
             SubCommand::with_name("state")
@@ -166,24 +422,80 @@ fn get_app() -> App<'static, 'static> {
         )
 }
 
+fn get_values(matches: &ArgMatches, name: &str) -> Vec<String> {
+    matches
+        .values_of(name)
+        .map(|values| values.map(|value| value.to_owned()).collect())
+        .unwrap_or_default()
+}
+
+/// Returns the value of a global arg. Its value is only recorded in the matches of the command it
+/// was given after and of that command's subcommands.
+fn get_global_value<'a>(matches: &'a ArgMatches<'_>, name: &str) -> Option<&'a str> {
+    matches
+        .subcommand()
+        .1
+        .and_then(|subcommand_matches| get_global_value(subcommand_matches, name))
+        .or_else(|| matches.value_of(name))
+}
+
 pub async fn run_with(args: Vec<String>) -> i32 {
     let app = get_app();
     let matches = app.get_matches_from(args);
-    match matches.subcommand() {
+    report::set_json_output(get_global_value(&matches, "output") == Some("json"));
+
+    let exit_code = match matches.subcommand() {
         ("deploy", Some(deploy_matches)) => {
-            commands::deploy::run(
-                deploy_matches.value_of("PROJECT"),
-                deploy_matches.value_of("environment"),
-                deploy_matches.is_present("allow_purchases"),
-            )
+            workspace::run_for_projects(deploy_matches, |project| async move {
+                commands::deploy::run(
+                    project.as_deref(),
+                    deploy_matches.value_of("environment"),
+                    deploy_matches.value_of("target_label"),
+                    deploy_matches.is_present("allow_purchases"),
+                    deploy_matches.is_present("yes"),
+                    !deploy_matches.is_present("no_push"),
+                    deploy_matches.value_of("report"),
+                    deploy_matches.is_present("step_summary"),
+                    deploy_matches.is_present("watch"),
+                    deploy_matches.value_of("debounce").unwrap(),
+                    deploy_matches.is_present("timings"),
+                    deploy_matches.value_of("slow_threshold").unwrap(),
+                )
+                .await
+            })
             .await
         }
         ("diff", Some(diff_matches)) => {
-            commands::diff::run(
-                diff_matches.value_of("PROJECT"),
-                diff_matches.value_of("environment"),
-                diff_matches.value_of("output"),
-                diff_matches.value_of("format"),
+            workspace::run_for_projects(diff_matches, |project| async move {
+                commands::diff::run(
+                    project.as_deref(),
+                    diff_matches.value_of("environment"),
+                    diff_matches.value_of("target_label"),
+                    diff_matches
+                        .value_of("from_env")
+                        .zip(diff_matches.value_of("to_env")),
+                    diff_matches.value_of("output_file"),
+                    diff_matches.value_of("format"),
+                    diff_matches.value_of("report"),
+                    diff_matches.is_present("step_summary"),
+                )
+                .await
+            })
+            .await
+        }
+        ("validate", Some(validate_matches)) => {
+            commands::validate::run(
+                validate_matches.value_of("PROJECT"),
+                validate_matches.value_of("format"),
+            )
+            .await
+        }
+        ("promote", Some(promote_matches)) => {
+            commands::promote::run(
+                promote_matches.value_of("PROJECT"),
+                promote_matches.value_of("FROM_ENVIRONMENT").unwrap(),
+                promote_matches.value_of("TO_ENVIRONMENT").unwrap(),
+                promote_matches.is_present("yes"),
             )
             .await
         }
@@ -191,23 +503,60 @@ pub async fn run_with(args: Vec<String>) -> i32 {
             commands::destroy::run(
                 destroy_matches.value_of("PROJECT"),
                 destroy_matches.value_of("environment"),
+                destroy_matches.is_present("yes"),
+                destroy_matches.is_present("dry_run"),
+                &get_values(destroy_matches, "target"),
+                &get_values(destroy_matches, "exclude"),
+                destroy_matches.is_present("keep_experience"),
             )
             .await
         }
-        ("outputs", Some(outputs_matches)) => {
-            commands::outputs::run(
-                outputs_matches.value_of("PROJECT"),
-                outputs_matches.value_of("environment"),
-                outputs_matches.value_of("output"),
-                outputs_matches.value_of("format").unwrap(),
+        ("gc", Some(gc_matches)) => {
+            commands::gc::run(
+                gc_matches.value_of("PROJECT"),
+                gc_matches.value_of("remote").unwrap(),
+                gc_matches.value_of("older_than"),
+                gc_matches.is_present("yes"),
+                gc_matches.is_present("dry_run"),
             )
             .await
         }
+        ("outputs", Some(outputs_matches)) => {
+            workspace::run_for_projects(outputs_matches, |project| async move {
+                commands::outputs::run(
+                    project.as_deref(),
+                    outputs_matches.value_of("environment"),
+                    outputs_matches.value_of("output_file"),
+                    outputs_matches.value_of("format").unwrap(),
+                    outputs_matches.value_of("get"),
+                    outputs_matches.is_present("labels"),
+                )
+                .await
+            })
+            .await
+        }
         ("import", Some(import_matches)) => {
-            commands::import::run(
+            // `import <resource-id> <roblox-id>` is accepted without a project
+            let (project, resource) = match (
                 import_matches.value_of("PROJECT"),
+                import_matches.value_of("RESOURCE_ID"),
+                import_matches.value_of("ROBLOX_ID"),
+            ) {
+                (project, Some(resource_id), Some(roblox_id)) => {
+                    (project, Some((resource_id, roblox_id)))
+                }
+                (Some(resource_id), Some(roblox_id), None) => {
+                    (None, Some((resource_id, roblox_id)))
+                }
+                (project, _, _) => (project, None),
+            };
+            commands::import::run(
+                project,
                 import_matches.value_of("environment"),
-                import_matches.value_of("target_id").unwrap(),
+                import_matches.value_of("target_id"),
+                resource,
+                import_matches.value_of("write_config"),
+                import_matches.is_present("reconcile"),
             )
             .await
         }
@@ -229,7 +578,20 @@ pub async fn run_with(args: Vec<String>) -> i32 {
             _ => unreachable!(),
         },
         _ => unreachable!(),
+    };
+
+    // Validation results have their own `--format` option
+    if let (command, Some(command_matches)) = matches.subcommand() {
+        if command != "validate" {
+            let command = match command_matches.subcommand_name() {
+                Some(subcommand) => format!("{} {}", command, subcommand),
+                None => command.to_owned(),
+            };
+            report::print(&command, exit_code);
+        }
     }
+
+    exit_code
 }
 
 pub async fn run() -> i32 {
@@ -0,0 +1,480 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use rbx_api::models::AssetId;
+use rbx_mantle::{
+    config::{CodegenConfig, CodegenFormat},
+    resource_graph::Resource,
+    roblox_resource_manager::{RobloxOutputs, RobloxResource},
+    state::ResourceStateVLatest,
+};
+
+use crate::commands::outputs::parse_resource_id;
+
+const HEADER: &str = "This file was generated by Mantle from the outputs of each environment. Do not edit it by hand.";
+
+enum CodegenValue {
+    Number(AssetId),
+    String(String),
+    Table(Vec<(String, CodegenValue)>),
+}
+
+/// The type of the outputs of every environment. Keys which are missing from some environments
+/// are optional, and values whose types differ between environments are `any`.
+enum CodegenType {
+    Number,
+    String,
+    Table(Vec<(String, CodegenType, bool)>),
+    Any,
+}
+
+impl CodegenType {
+    fn of(value: &CodegenValue) -> Self {
+        match value {
+            CodegenValue::Number(_) => Self::Number,
+            CodegenValue::String(_) => Self::String,
+            CodegenValue::Table(entries) => Self::Table(
+                entries
+                    .iter()
+                    .map(|(key, value)| (key.clone(), Self::of(value), false))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn merge(self, value: &CodegenValue) -> Self {
+        match (self, value) {
+            (Self::Number, CodegenValue::Number(_)) => Self::Number,
+            (Self::String, CodegenValue::String(_)) => Self::String,
+            (Self::Table(mut entries), CodegenValue::Table(values)) => {
+                entries = entries
+                    .into_iter()
+                    .map(|(key, entry_type, optional)| {
+                        match values.iter().find(|(value_key, _)| *value_key == key) {
+                            Some((_, value)) => (key, entry_type.merge(value), optional),
+                            None => (key, entry_type, true),
+                        }
+                    })
+                    .collect();
+                for (key, value) in values {
+                    if !entries.iter().any(|(entry_key, _, _)| entry_key == key) {
+                        entries.push((key.clone(), Self::of(value), true));
+                    }
+                }
+                Self::Table(entries)
+            }
+            _ => Self::Any,
+        }
+    }
+}
+
+#[derive(Default)]
+struct TargetOutputs {
+    experience_id: Option<AssetId>,
+    start_place_id: Option<AssetId>,
+    places: BTreeMap<String, AssetId>,
+    products: BTreeMap<String, AssetId>,
+    passes: BTreeMap<String, AssetId>,
+    badges: BTreeMap<String, AssetId>,
+    assets: BTreeMap<String, AssetId>,
+    // Asset aliases are of the form `Images/<name>` or `Audio/<name>`
+    asset_aliases: BTreeMap<String, String>,
+}
+
+impl TargetOutputs {
+    fn into_value(self) -> CodegenValue {
+        let mut entries = Vec::new();
+        if let Some(experience_id) = self.experience_id {
+            entries.push((
+                "ExperienceId".to_owned(),
+                CodegenValue::Number(experience_id),
+            ));
+        }
+        if let Some(start_place_id) = self.start_place_id {
+            entries.push((
+                "StartPlaceId".to_owned(),
+                CodegenValue::Number(start_place_id),
+            ));
+        }
+
+        let mut images = BTreeMap::new();
+        let mut audio = BTreeMap::new();
+        for (file, alias) in self.asset_aliases.iter() {
+            let asset_id = match self.assets.get(file) {
+                Some(v) => *v,
+                None => continue,
+            };
+            match alias.split_once('/') {
+                Some(("Images", name)) => images.insert(name.to_owned(), asset_id),
+                Some(("Audio", name)) => audio.insert(name.to_owned(), asset_id),
+                _ => continue,
+            };
+        }
+
+        for (name, ids) in [
+            ("Places", self.places),
+            ("Products", self.products),
+            ("Passes", self.passes),
+            ("Badges", self.badges),
+            ("Images", images),
+            ("Audio", audio),
+        ] {
+            entries.push((
+                name.to_owned(),
+                CodegenValue::Table(
+                    ids.into_iter()
+                        .map(|(label, id)| (label, CodegenValue::Number(id)))
+                        .collect(),
+                ),
+            ));
+        }
+
+        CodegenValue::Table(entries)
+    }
+}
+
+/// Collects the IDs which code may need from an environment's resources, keyed by the labels used
+/// in the configuration. Projects with labelled targets are keyed by the target label first.
+fn get_environment_value(environment_label: &str, resources: &[RobloxResource]) -> CodegenValue {
+    let mut targets: BTreeMap<Option<&str>, TargetOutputs> = BTreeMap::new();
+    for resource in resources {
+        let resource_id = resource.get_id();
+        let (target_label, resource_type, label) = parse_resource_id(&resource_id);
+        let target = targets.entry(target_label).or_default();
+        match (resource_type, resource.get_outputs()) {
+            ("experience", Some(RobloxOutputs::Experience(outputs))) => {
+                target.experience_id = Some(outputs.asset_id);
+                target.start_place_id = Some(outputs.start_place_id);
+            }
+            ("place", Some(RobloxOutputs::Place(outputs))) => {
+                target.places.insert(label.to_owned(), outputs.asset_id);
+            }
+            ("product", Some(RobloxOutputs::Product(outputs))) => {
+                target.products.insert(label.to_owned(), outputs.product_id);
+            }
+            ("pass", Some(RobloxOutputs::Pass(outputs))) => {
+                target.passes.insert(label.to_owned(), outputs.asset_id);
+            }
+            ("badge", Some(RobloxOutputs::Badge(outputs))) => {
+                target.badges.insert(label.to_owned(), outputs.asset_id);
+            }
+            ("asset", Some(RobloxOutputs::ImageAsset(outputs))) => {
+                target.assets.insert(label.to_owned(), outputs.asset_id);
+            }
+            ("asset", Some(RobloxOutputs::AudioAsset(outputs))) => {
+                target.assets.insert(label.to_owned(), outputs.asset_id);
+            }
+            ("assetAlias", Some(RobloxOutputs::AssetAlias(outputs))) => {
+                target.asset_aliases.insert(label.to_owned(), outputs.name);
+            }
+            _ => {}
+        }
+    }
+
+    let mut entries = vec![(
+        "Environment".to_owned(),
+        CodegenValue::String(environment_label.to_owned()),
+    )];
+    if targets.keys().all(Option::is_none) {
+        if let Some(CodegenValue::Table(target_entries)) =
+            targets.remove(&None).map(|target| target.into_value())
+        {
+            entries.extend(target_entries);
+        }
+    } else {
+        for (target_label, target) in targets {
+            entries.push((
+                target_label.unwrap_or_default().to_owned(),
+                target.into_value(),
+            ));
+        }
+    }
+    CodegenValue::Table(entries)
+}
+
+/// Returns the experience IDs of an environment so that the generated module can select the
+/// environment of the running experience.
+fn get_experience_ids(resources: &[RobloxResource]) -> Vec<AssetId> {
+    resources
+        .iter()
+        .filter_map(|resource| match resource.get_outputs() {
+            Some(RobloxOutputs::Experience(outputs)) => Some(outputs.asset_id),
+            _ => None,
+        })
+        .collect()
+}
+
+fn escape_string(value: &str) -> String {
+    format!(
+        "\"{}\"",
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    )
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+const LUAU_KEYWORDS: &[&str] = &[
+    "and", "break", "continue", "do", "else", "elseif", "end", "export", "false", "for",
+    "function", "if", "in", "local", "nil", "not", "or", "repeat", "return", "then", "true",
+    "type", "typeof", "until", "while",
+];
+
+fn get_luau_key(key: &str) -> String {
+    if is_identifier(key) && !LUAU_KEYWORDS.contains(&key) {
+        key.to_owned()
+    } else {
+        format!("[{}]", escape_string(key))
+    }
+}
+
+fn get_ts_key(key: &str) -> String {
+    if is_identifier(key) {
+        key.to_owned()
+    } else {
+        escape_string(key)
+    }
+}
+
+struct Syntax {
+    get_key: fn(&str) -> String,
+    assignment: &'static str,
+    type_separator: char,
+    any_type: &'static str,
+    // Luau marks optional types (`Key: number?`) while TypeScript marks optional keys
+    // (`Key?: number`)
+    optional_key_suffix: &'static str,
+    optional_type_suffix: &'static str,
+}
+
+const LUAU_SYNTAX: Syntax = Syntax {
+    get_key: get_luau_key,
+    assignment: " = ",
+    type_separator: ',',
+    any_type: "any",
+    optional_key_suffix: "",
+    optional_type_suffix: "?",
+};
+
+const TS_SYNTAX: Syntax = Syntax {
+    get_key: get_ts_key,
+    assignment: ": ",
+    type_separator: ';',
+    any_type: "unknown",
+    optional_key_suffix: "?",
+    optional_type_suffix: "",
+};
+
+fn write_table<T, F>(out: &mut String, entries: &[T], depth: usize, write_entry: F)
+where
+    F: Fn(&mut String, &T),
+{
+    if entries.is_empty() {
+        out.push_str("{}");
+        return;
+    }
+    out.push_str("{\n");
+    for entry in entries {
+        out.push_str(&"\t".repeat(depth + 1));
+        write_entry(out, entry);
+        out.push('\n');
+    }
+    out.push_str(&"\t".repeat(depth));
+    out.push('}');
+}
+
+fn write_type(out: &mut String, syntax: &Syntax, value_type: &CodegenType, depth: usize) {
+    match value_type {
+        CodegenType::Number => out.push_str("number"),
+        CodegenType::String => out.push_str("string"),
+        CodegenType::Any => out.push_str(syntax.any_type),
+        CodegenType::Table(entries) => {
+            write_table(out, entries, depth, |out, (key, entry_type, optional)| {
+                out.push_str(&(syntax.get_key)(key));
+                if *optional {
+                    out.push_str(syntax.optional_key_suffix);
+                }
+                out.push_str(": ");
+                write_type(out, syntax, entry_type, depth + 1);
+                if *optional {
+                    out.push_str(syntax.optional_type_suffix);
+                }
+                out.push(syntax.type_separator);
+            })
+        }
+    }
+}
+
+fn write_value(out: &mut String, syntax: &Syntax, value: &CodegenValue, depth: usize) {
+    match value {
+        CodegenValue::Number(number) => out.push_str(&number.to_string()),
+        CodegenValue::String(string) => out.push_str(&escape_string(string)),
+        CodegenValue::Table(entries) => write_table(out, entries, depth, |out, (key, value)| {
+            out.push_str(&format!("{}{}", (syntax.get_key)(key), syntax.assignment));
+            write_value(out, syntax, value, depth + 1);
+            out.push(',');
+        }),
+    }
+}
+
+fn get_luau_module(
+    environment_label: &str,
+    environments: &CodegenValue,
+    outputs_type: Option<&CodegenType>,
+    experience_ids: &[(AssetId, String)],
+) -> String {
+    let mut out = format!("-- {}\n\nexport type Outputs = ", HEADER);
+    match outputs_type {
+        Some(outputs_type) => write_type(&mut out, &LUAU_SYNTAX, outputs_type, 0),
+        None => out.push_str("{}"),
+    }
+
+    out.push_str("\n\nlocal environments: { [string]: Outputs } = ");
+    write_value(&mut out, &LUAU_SYNTAX, environments, 0);
+
+    out.push_str("\n\nlocal environmentsByGameId: { [number]: string } = {\n");
+    for (experience_id, label) in experience_ids {
+        out.push_str(&format!(
+            "\t[{}] = {},\n",
+            experience_id,
+            escape_string(label)
+        ));
+    }
+    out.push_str("}\n\n");
+
+    out.push_str(&format!(
+        "return environments[environmentsByGameId[game.GameId] or {}]\n",
+        escape_string(environment_label)
+    ));
+    out
+}
+
+fn get_ts_module(
+    environment_label: &str,
+    environments: &CodegenValue,
+    outputs_type: Option<&CodegenType>,
+    experience_ids: &[(AssetId, String)],
+) -> String {
+    let mut out = format!("// {}\n\nexport type Outputs = ", HEADER);
+    match outputs_type {
+        Some(outputs_type) => write_type(&mut out, &TS_SYNTAX, outputs_type, 0),
+        None => out.push_str("{}"),
+    }
+
+    out.push_str(";\n\nconst environments: Record<string, Outputs> = ");
+    write_value(&mut out, &TS_SYNTAX, environments, 0);
+
+    out.push_str(";\n\nconst environmentsByGameId: Record<number, string> = {\n");
+    for (experience_id, label) in experience_ids {
+        out.push_str(&format!("\t{}: {},\n", experience_id, escape_string(label)));
+    }
+    out.push_str("};\n\n");
+
+    out.push_str(&format!(
+        "const outputs = environments[environmentsByGameId[game.GameId] ?? {}];\n\nexport default outputs;\n",
+        escape_string(environment_label)
+    ));
+    out
+}
+
+/// Generates a module containing the outputs of every environment in the state which selects the
+/// environment of the running experience, falling back to the given environment.
+pub fn get_codegen_module(
+    format: CodegenFormat,
+    environment_label: &str,
+    state: &ResourceStateVLatest,
+) -> String {
+    let environments = CodegenValue::Table(
+        state
+            .environments
+            .iter()
+            .map(|(label, resources)| (label.clone(), get_environment_value(label, resources)))
+            .collect(),
+    );
+    // The type covers every environment since the module may select any of them at runtime
+    let outputs_type = match &environments {
+        CodegenValue::Table(entries) => entries.iter().fold(None, |outputs_type, (_, value)| {
+            Some(match outputs_type {
+                Some(outputs_type) => CodegenType::merge(outputs_type, value),
+                None => CodegenType::of(value),
+            })
+        }),
+        _ => None,
+    };
+    let experience_ids = state
+        .environments
+        .iter()
+        .flat_map(|(label, resources)| {
+            get_experience_ids(resources)
+                .into_iter()
+                .map(move |experience_id| (experience_id, label.clone()))
+        })
+        .collect::<Vec<_>>();
+
+    match format {
+        CodegenFormat::Luau => get_luau_module(
+            environment_label,
+            &environments,
+            outputs_type.as_ref(),
+            &experience_ids,
+        ),
+        CodegenFormat::Ts => get_ts_module(
+            environment_label,
+            &environments,
+            outputs_type.as_ref(),
+            &experience_ids,
+        ),
+    }
+}
+
+fn get_codegen_format(codegen_config: &CodegenConfig) -> Result<CodegenFormat, String> {
+    if let Some(format) = codegen_config.format {
+        return Ok(format);
+    }
+    match Path::new(&codegen_config.path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("lua" | "luau") => Ok(CodegenFormat::Luau),
+        Some("ts") => Ok(CodegenFormat::Ts),
+        _ => Err(format!(
+            "Unable to determine the codegen format for {}. Specify a format of luau or ts.",
+            codegen_config.path
+        )),
+    }
+}
+
+/// Writes each of the project's `codegen` modules.
+pub fn write_codegen_files(
+    project_path: &Path,
+    codegen: &[CodegenConfig],
+    environment_label: &str,
+    state: &ResourceStateVLatest,
+) -> Result<u32, String> {
+    let mut count = 0;
+    for codegen_config in codegen {
+        let format = get_codegen_format(codegen_config)?;
+        let module = get_codegen_module(format, environment_label, state);
+
+        let path = project_path.join(&codegen_config.path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| {
+                format!("Unable to create directory: {}\n\t{}", parent.display(), e)
+            })?;
+        }
+        // Avoid touching the file when nothing changed so file watchers (e.g. Rojo) stay quiet
+        if fs::read_to_string(&path).ok().as_deref() == Some(module.as_str()) {
+            continue;
+        }
+        fs::write(&path, module)
+            .map_err(|e| format!("Unable to write codegen file: {}\n\t{}", path.display(), e))?;
+        logger::log(format!("Wrote {}", path.display()));
+        count += 1;
+    }
+    Ok(count)
+}
@@ -1,142 +1,601 @@
-use std::{path::PathBuf, process::Command, str};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
 
+use git2::{Cred, CredentialType, ObjectType, PushOptions, RemoteCallbacks, Repository};
 use yansi::Paint;
 
+use crate::{
+    approval::approve_changes,
+    codegen::write_codegen_files,
+    markdown_report::{emit_github_annotations, get_markdown_report, write_markdown_report},
+    notify::notify_deployment,
+    report::{self, get_target_results, TargetResult},
+    timings::{log_timings, Timings},
+};
+
 use rbx_mantle::{
-    config::{load_project_config, TargetConfig},
-    project::{load_project, Project},
-    resource_graph::{EvaluateResults, ResourceGraph},
+    config::{
+        get_project_config_files, load_project_config, CodegenConfig, EnvironmentConfig,
+        OwnerConfig, StateConfig, TargetConfig,
+    },
+    project::{
+        find_environment, get_environment_owner, get_environment_targets, load_project,
+        resolve_environments, Project, ProjectTarget,
+    },
+    resource_graph::{EvaluateResults, Resource, ResourceGraph},
     roblox_resource_manager::{RobloxInputs, RobloxOutputs, RobloxResource, RobloxResourceManager},
-    state::{get_desired_graph, save_state},
+    state::{
+        get_desired_graph, get_target_desired_graph, record_deployment, save_state,
+        ResourceStateVLatest,
+    },
 };
 
-fn run_command(dir: PathBuf, command: &str) -> std::io::Result<std::process::Output> {
-    if cfg!(target_os = "windows") {
-        return Command::new("cmd")
-            .current_dir(dir)
-            .arg("/C")
-            .arg(command)
-            .output();
-    } else {
-        return Command::new("sh")
-            .current_dir(dir)
-            .arg("-c")
-            .arg(command)
-            .output();
+// How often watched files are checked for changes
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Pushes the tags to the remote, failing if the remote rejects any of them.
+fn push_tags(repo: &Repository, remote: &str, tags: &[String]) -> Result<(), git2::Error> {
+    let config = repo.config()?;
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(|url, username, allowed| {
+        if allowed.contains(CredentialType::SSH_KEY) {
+            Cred::ssh_key_from_agent(username.unwrap_or("git"))
+        } else if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            Cred::credential_helper(&config, url, username)
+        } else {
+            Cred::default()
+        }
+    });
+    callbacks.push_update_reference(|refname, status| match status {
+        Some(message) => Err(git2::Error::from_str(&format!(
+            "{} was rejected: {}",
+            refname, message
+        ))),
+        None => Ok(()),
+    });
+    let mut push_options = PushOptions::new();
+    push_options.remote_callbacks(callbacks);
+
+    let refspecs = tags
+        .iter()
+        .map(|tag| format!("refs/tags/{0}:refs/tags/{0}", tag))
+        .collect::<Vec<_>>();
+    repo.find_remote(remote)?
+        .push(&refspecs, Some(&mut push_options))
+}
+
+/// Deletes tags created by a deployment which could not be completed.
+fn delete_tags(repo: &Repository, tags: &[String]) {
+    for tag in tags {
+        if let Err(e) = repo.tag_delete(tag) {
+            logger::log(Paint::red(format!("Unable to delete tag {}: {}", tag, e)));
+        }
     }
 }
 
+fn get_tag(
+    template: &str,
+    environment_label: &str,
+    target_label: Option<&str>,
+    label: &str,
+    version: u64,
+) -> String {
+    template
+        .replace("{env}", environment_label)
+        .replace("{target}", target_label.unwrap_or_default())
+        .replace("{label}", label)
+        .replace("{version}", &version.to_string())
+}
+
 fn tag_commit(
-    project_path: PathBuf,
-    target_config: &TargetConfig,
+    project_path: &Path,
+    environment_config: &EnvironmentConfig,
+    targets: &[ProjectTarget],
     next_graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
     previous_graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+    results: &EvaluateResults,
+    push: bool,
 ) -> Result<u32, String> {
-    let mut tag_count: u32 = 0;
-
-    match target_config {
-        TargetConfig::Experience(target_config) => {
-            for label in target_config.places.as_ref().unwrap().keys() {
-                let resource_id = format!("placeFile_{}", label);
-
-                let previous_outputs = previous_graph.get_outputs(&resource_id);
-                let next_outputs = next_graph.get_outputs(&resource_id);
-
-                let tag_version = match (previous_outputs, next_outputs) {
-                    (None, Some(RobloxOutputs::PlaceFile(next))) => Some(next.version),
-                    (
-                        Some(RobloxOutputs::PlaceFile(previous)),
-                        Some(RobloxOutputs::PlaceFile(next)),
-                    ) if next.version != previous.version => Some(next.version),
-                    _ => None,
-                };
-
-                if let Some(version) = tag_version {
-                    logger::log(format!(
-                        "Place {} was updated to version {}",
-                        Paint::cyan(label),
-                        Paint::cyan(version)
-                    ));
-                    let tag = format!("{}-v{}", label, version);
-                    logger::log(format!("Tagging commit with {}", Paint::cyan(tag.clone())));
-
-                    tag_count += 1;
-                    run_command(project_path.clone(), &format!("git tag {}", tag))
-                        .map_err(|e| format!("Unable to tag the current commit\n\t{}", e))?;
+    let mut tags: Vec<(String, String)> = Vec::new();
+
+    for target in targets {
+        match &target.config {
+            TargetConfig::Experience(target_config) => {
+                for label in target_config.places.as_ref().unwrap().keys() {
+                    let resource_id = target.resource_id(&format!("placeFile_{}", label));
+
+                    let previous_outputs = previous_graph.get_outputs(&resource_id);
+                    let next_outputs = next_graph.get_outputs(&resource_id);
+
+                    let tag_version = match (previous_outputs, next_outputs) {
+                        (None, Some(RobloxOutputs::PlaceFile(next))) => Some(next.version),
+                        (
+                            Some(RobloxOutputs::PlaceFile(previous)),
+                            Some(RobloxOutputs::PlaceFile(next)),
+                        ) if next.version != previous.version => Some(next.version),
+                        _ => None,
+                    };
+
+                    if let Some(version) = tag_version {
+                        logger::log(format!(
+                            "Place {} was updated to version {}",
+                            Paint::cyan(label),
+                            Paint::cyan(version)
+                        ));
+                        let template = match (&environment_config.tag_template, &target.label) {
+                            (Some(template), _) => template.as_str(),
+                            (None, Some(_)) => "{target}-{label}-v{version}",
+                            (None, None) => "{label}-v{version}",
+                        };
+                        let tag = get_tag(
+                            template,
+                            &environment_config.label,
+                            target.label.as_deref(),
+                            label,
+                            version,
+                        );
+                        logger::log(format!("Tagging commit with {}", Paint::cyan(&tag)));
+
+                        let message = format!(
+                            "Deployed place {} version {} to environment {}\n\n{} create(s), {} update(s), {} delete(s), {} noop(s), {} skip(s)",
+                            label,
+                            version,
+                            environment_config.label,
+                            results.created_count,
+                            results.updated_count,
+                            results.deleted_count,
+                            results.noop_count,
+                            results.skipped_count
+                        );
+                        tags.push((tag, message));
+                    }
                 }
             }
         }
     }
 
-    if tag_count > 0 {
-        run_command(project_path, "git push --tags")
-            .map_err(|e| format!("Unable to push tags to remote\n\t{}", e))?;
+    if tags.is_empty() {
+        return Ok(0);
     }
 
-    Ok(tag_count)
+    let repo = Repository::discover(project_path)
+        .map_err(|e| format!("Unable to open the git repository\n\t{}", e))?;
+    let commit = repo
+        .head()
+        .and_then(|head| head.peel(ObjectType::Commit))
+        .map_err(|e| format!("Unable to find the current commit\n\t{}", e))?;
+    let signature = repo
+        .signature()
+        .map_err(|e| format!("Unable to find the git user to tag as\n\t{}", e))?;
+
+    // Tags are only kept if all of them are created and pushed so that a failed deployment can be
+    // retried without conflicting with its own tags
+    let mut created_tags: Vec<String> = Vec::new();
+    for (tag, message) in tags.iter() {
+        if let Err(e) = repo.tag(tag, &commit, &signature, message, false) {
+            delete_tags(&repo, &created_tags);
+            return Err(format!(
+                "Unable to tag the current commit with {}\n\t{}",
+                tag, e
+            ));
+        }
+        created_tags.push(tag.clone());
+    }
+
+    if push {
+        let remote = environment_config.tag_remote.as_deref().unwrap_or("origin");
+        logger::log(format!("Pushing tags to {}", Paint::cyan(remote)));
+        if let Err(e) = push_tags(&repo, remote, &created_tags) {
+            delete_tags(&repo, &created_tags);
+            return Err(format!("Unable to push tags to {}\n\t{}", remote, e));
+        }
+    }
+
+    Ok(tags.len() as u32)
 }
 
-fn log_target_results(
-    target_config: &TargetConfig,
-    graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
-) {
+fn log_target_results(target_results: &[TargetResult]) {
     logger::start_action("Target results:");
-    match target_config {
-        TargetConfig::Experience(target_config) => {
-            let experience_outputs = match graph.get_outputs("experience_singleton") {
-                Some(RobloxOutputs::Experience(outputs)) => Some(outputs),
-                _ => None,
-            };
-            logger::log("Experience:");
-            if let Some(outputs) = experience_outputs {
-                logger::log(format!(
-                    "  https://www.roblox.com/games/{}",
-                    outputs.start_place_id
-                ));
+    for target_result in target_results {
+        if let Some(label) = &target_result.label {
+            logger::log(format!("Target {}:", Paint::cyan(label)));
+        }
+        logger::log("Experience:");
+        if let Some(url) = &target_result.experience_url {
+            logger::log(format!("  {}", url));
+        } else {
+            logger::log(Paint::red("  no outputs"));
+        }
+        logger::log("");
+
+        logger::log("Places:");
+        for (label, place_url) in target_result.place_urls.iter() {
+            if let Some(url) = place_url {
+                logger::log(format!("  {}: {}", label, url));
             } else {
-                logger::log(Paint::red("  no outputs"));
+                logger::log(format!("  {}: {}", label, Paint::red("no outputs")));
             }
-            logger::log("");
-
-            logger::log("Places:");
-            for label in target_config.places.as_ref().unwrap().keys() {
-                let resource_id = format!("place_{}", label);
-
-                let place_outputs = match graph.get_outputs(&resource_id) {
-                    Some(RobloxOutputs::Place(outputs)) => Some(outputs),
-                    _ => None,
-                };
-                if let Some(outputs) = place_outputs {
-                    logger::log(format!(
-                        "  {}: https://www.roblox.com/games/{}",
-                        label, outputs.asset_id
-                    ));
-                } else {
-                    logger::log(format!("  {}: {}", label, Paint::red("no outputs")));
+        }
+        logger::log("");
+    }
+    logger::end_action_without_message();
+}
+
+fn get_next_graph(
+    project_path: &Path,
+    targets: &[ProjectTarget],
+    target_label: Option<&str>,
+    owner_config: &OwnerConfig,
+    current_graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+) -> Result<ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>, String> {
+    match target_label {
+        Some(target_label) => get_target_desired_graph(
+            project_path,
+            targets,
+            target_label,
+            owner_config,
+            current_graph,
+        ),
+        None => get_desired_graph(project_path, targets, owner_config),
+    }
+}
+
+fn log_evaluate_results(results: &Result<EvaluateResults, String>) {
+    match results {
+        Ok(results) => {
+            match results {
+                EvaluateResults {
+                    created_count: 0,
+                    updated_count: 0,
+                    deleted_count: 0,
+                    skipped_count: 0,
+                    ..
+                } => logger::end_action("No changes required"),
+                EvaluateResults {
+                    created_count,
+                    updated_count,
+                    deleted_count,
+                    noop_count,
+                    skipped_count,
+                    ..
+                } => logger::end_action(format!(
+                    "Succeeded with {} create(s), {} update(s), {} delete(s), {} noop(s), {} skip(s)",
+                    created_count, updated_count, deleted_count, noop_count, skipped_count
+                )),
+            };
+        }
+        Err(e) => {
+            logger::end_action(Paint::red(report::error(e)));
+        }
+    };
+}
+
+/// Returns the project's config files and every file which the resources in the graph are
+/// created from.
+fn get_watched_files(
+    project: Option<&str>,
+    project_path: &Path,
+    graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+) -> Result<Vec<PathBuf>, String> {
+    let mut files = get_project_config_files(project)?;
+    for resource in graph.get_resource_list() {
+        let inputs = resource.get_inputs();
+        for file_path in inputs.get_file_paths() {
+            files.push(project_path.join(file_path));
+        }
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+fn get_modified_times(files: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|file| {
+            file.metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .collect()
+}
+
+/// Writes the project's generated code files for the environment.
+fn generate_code(
+    project_path: &Path,
+    codegen: &[CodegenConfig],
+    environment_label: &str,
+    state: &ResourceStateVLatest,
+) {
+    if codegen.is_empty() {
+        return;
+    }
+
+    logger::start_action("Generating code:");
+    match write_codegen_files(project_path, codegen, environment_label, state) {
+        Ok(0) => logger::end_action("No changes required"),
+        Ok(count) => logger::end_action(format!("Succeeded in writing {} file(s)", count)),
+        Err(e) => logger::end_action(Paint::red(report::error(e))),
+    }
+}
+
+/// Sends the notifications of a deployment which failed before its resources were deployed and
+/// returns the failure exit code.
+async fn fail_deployment(
+    project_path: &Path,
+    environment_config: &EnvironmentConfig,
+    variables: &BTreeMap<String, String>,
+    targets: &[ProjectTarget],
+    current_graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+    error: &str,
+) -> i32 {
+    notify_deployment(
+        project_path,
+        environment_config,
+        variables,
+        targets,
+        current_graph,
+        current_graph,
+        None,
+        Some(error),
+    )
+    .await;
+    1
+}
+
+/// Keeps a project's resource manager and resource graph in memory between deployments so that
+/// changes can be deployed without reloading state or authenticating again.
+struct Watcher<'a> {
+    project: Option<&'a str>,
+    target_label: Option<&'a str>,
+    allow_purchases: bool,
+    project_path: PathBuf,
+    environment_config: EnvironmentConfig,
+    targets: Vec<ProjectTarget>,
+    state: ResourceStateVLatest,
+    state_config: StateConfig,
+    current_graph: ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+    resource_manager: RobloxResourceManager,
+    watched_files: Vec<PathBuf>,
+    codegen: Vec<CodegenConfig>,
+}
+
+impl Watcher<'_> {
+    fn load_next_graph(
+        &mut self,
+    ) -> Result<ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>, String> {
+        let (project_path, config) = load_project_config(self.project)?;
+        let environments = resolve_environments(&config.environments)?;
+        let environment_config = find_environment(&environments, &self.environment_config.label)
+            .ok_or(format!(
+                "No environment configuration found with name {}",
+                self.environment_config.label
+            ))?;
+        let targets = get_environment_targets(project_path.clone(), &config, &environment_config)?;
+        let (owner_config, _) = get_environment_owner(&config, &environment_config)?;
+        let next_graph = get_next_graph(
+            &project_path,
+            &targets,
+            self.target_label,
+            &owner_config,
+            &self.current_graph,
+        )?;
+        self.targets = targets;
+        self.codegen = config.codegen;
+        Ok(next_graph)
+    }
+
+    /// Returns the resources to evaluate after the files changed: the resources created from the
+    /// changed files and their dependents, or every resource if any other file, such as a config
+    /// file, changed.
+    fn get_changed_resources(
+        &self,
+        next_graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+        changed_files: &[PathBuf],
+    ) -> Option<BTreeSet<String>> {
+        let mut changed_resources = BTreeSet::new();
+        let mut resource_files = BTreeSet::new();
+        for resource in next_graph.get_resource_list() {
+            for file_path in resource.get_inputs().get_file_paths() {
+                let file = self.project_path.join(file_path);
+                if changed_files.contains(&file) {
+                    changed_resources.insert(resource.get_id());
                 }
+                resource_files.insert(file);
             }
         }
+
+        if changed_files
+            .iter()
+            .all(|file| resource_files.contains(file))
+        {
+            Some(next_graph.get_dependents(&changed_resources))
+        } else {
+            None
+        }
+    }
+
+    async fn deploy_changes(&mut self, changed_files: &[PathBuf]) {
+        logger::start_action("Loading project:");
+        let mut next_graph = match self.load_next_graph() {
+            Ok(v) => v,
+            Err(e) => {
+                logger::end_action(Paint::red(e));
+                return;
+            }
+        };
+        let changed_resources = self.get_changed_resources(&next_graph, changed_files);
+        match get_watched_files(self.project, &self.project_path, &next_graph) {
+            Ok(watched_files) => self.watched_files = watched_files,
+            Err(e) => {
+                logger::end_action(Paint::red(e));
+                return;
+            }
+        }
+        logger::end_action("Succeeded");
+
+        // Only the resources affected by the changed files are evaluated unless the config changed
+        logger::start_action("Deploying resources:");
+        let results = next_graph
+            .evaluate_resources(
+                &self.current_graph,
+                &mut self.resource_manager,
+                self.allow_purchases,
+                changed_resources.as_ref(),
+            )
+            .await
+            .and_then(|results| results.into_result());
+        log_evaluate_results(&results);
+
+        logger::start_action("Saving state:");
+        record_deployment(&mut self.state, &self.environment_config);
+        self.state.environments.insert(
+            self.environment_config.label.clone(),
+            next_graph.get_resource_list(),
+        );
+        match save_state(&self.project_path, &self.state_config, &self.state).await {
+            Ok(_) => logger::end_action("Succeeded"),
+            Err(e) => logger::end_action(Paint::red(e)),
+        };
+
+        generate_code(
+            &self.project_path,
+            &self.codegen,
+            &self.environment_config.label,
+            &self.state,
+        );
+
+        log_target_results(&get_target_results(&self.targets, &next_graph));
+        self.current_graph = next_graph;
+    }
+
+    async fn run(mut self, debounce: Duration) -> i32 {
+        let interrupt = tokio::signal::ctrl_c();
+        tokio::pin!(interrupt);
+
+        let mut modified_times = get_modified_times(&self.watched_files);
+        let mut changed_at: Option<Instant> = None;
+        let mut changed_files: Vec<PathBuf> = Vec::new();
+        logger::log(format!(
+            "Watching {} file(s) for changes. Press Ctrl+C to stop.",
+            Paint::cyan(self.watched_files.len())
+        ));
+
+        loop {
+            // An interrupt received while deploying stops the watcher once the deployment has
+            // finished and its state has been saved
+            tokio::select! {
+                _ = &mut interrupt => {
+                    logger::log("Stopped watching");
+                    return 0;
+                }
+                _ = tokio::time::sleep(WATCH_POLL_INTERVAL) => {}
+            }
+
+            let next_modified_times = get_modified_times(&self.watched_files);
+            if next_modified_times != modified_times {
+                for (file, _) in self
+                    .watched_files
+                    .iter()
+                    .zip(next_modified_times.iter().zip(modified_times.iter()))
+                    .filter(|(_, (next, previous))| next != previous)
+                {
+                    logger::log(format!("Changed {}", Paint::cyan(file.display())));
+                    if !changed_files.contains(file) {
+                        changed_files.push(file.clone());
+                    }
+                }
+                modified_times = next_modified_times;
+                changed_at = Some(Instant::now());
+                continue;
+            }
+
+            // Wait for files to stop changing so that saving several files deploys them together
+            match changed_at {
+                Some(instant) if instant.elapsed() >= debounce => changed_at = None,
+                _ => continue,
+            }
+
+            self.deploy_changes(&changed_files).await;
+            changed_files.clear();
+
+            modified_times = get_modified_times(&self.watched_files);
+            logger::log(format!(
+                "Watching {} file(s) for changes. Press Ctrl+C to stop.",
+                Paint::cyan(self.watched_files.len())
+            ));
+        }
     }
-    logger::end_action_without_message();
 }
 
-pub async fn run(project: Option<&str>, environment: Option<&str>, allow_purchases: bool) -> i32 {
+#[allow(clippy::too_many_arguments)]
+pub async fn run(
+    project: Option<&str>,
+    environment: Option<&str>,
+    target_label: Option<&str>,
+    allow_purchases: bool,
+    yes: bool,
+    push: bool,
+    report_file: Option<&str>,
+    step_summary: bool,
+    watch: bool,
+    debounce: &str,
+    show_timings: bool,
+    slow_threshold: &str,
+) -> i32 {
+    let debounce = match debounce.parse::<u64>() {
+        Ok(v) => Duration::from_millis(v),
+        Err(e) => {
+            logger::log(Paint::red(report::error(format!(
+                "Debounce {} is invalid: {}",
+                debounce, e
+            ))));
+            return 1;
+        }
+    };
+    let slow_threshold = match slow_threshold.parse::<u64>() {
+        Ok(v) => Duration::from_millis(v),
+        Err(e) => {
+            logger::log(Paint::red(report::error(format!(
+                "Slow threshold {} is invalid: {}",
+                slow_threshold, e
+            ))));
+            return 1;
+        }
+    };
+    if watch && report::is_json_output() {
+        logger::log(Paint::red(report::error(
+            "Watch mode cannot be used with JSON output",
+        )));
+        return 1;
+    }
+
+    let mut timings = Timings::start();
+
     logger::start_action("Loading project:");
+    let start = Instant::now();
     let (project_path, config) = match load_project_config(project) {
         Ok(v) => v,
         Err(e) => {
-            logger::end_action(Paint::red(e));
+            logger::end_action(Paint::red(report::error(e)));
             return 1;
         }
     };
+    timings.record("Load config", start);
+    let codegen = config.codegen.clone();
+    let start = Instant::now();
     let Project {
         current_graph,
         mut state,
         environment_config,
-        target_config,
+        targets,
         payment_source,
         state_config,
         owner_config,
+        variables,
     } = match load_project(project_path.clone(), config, environment).await {
         Ok(Some(v)) => v,
         Ok(None) => {
@@ -144,91 +603,237 @@ pub async fn run(project: Option<&str>, environment: Option<&str>, allow_purchas
             return 0;
         }
         Err(e) => {
-            logger::end_action(Paint::red(e));
+            logger::end_action(Paint::red(report::error(e)));
             return 1;
         }
     };
-    let mut next_graph =
-        match get_desired_graph(project_path.as_path(), &target_config, &owner_config) {
+    timings.record("Load state", start);
+    // Refused options and cancellations are not failed deployments, so they are not notified
+    if watch && environment_config.protected {
+        logger::end_action(Paint::red(report::error(format!(
+            "Watch mode cannot be used with the protected environment {}",
+            environment_config.label
+        ))));
+        return 1;
+    }
+    let start = Instant::now();
+    let mut next_graph = match get_next_graph(
+        &project_path,
+        &targets,
+        target_label,
+        &owner_config,
+        &current_graph,
+    ) {
+        Ok(v) => v,
+        Err(e) => {
+            logger::end_action(Paint::red(report::error(&e)));
+            return fail_deployment(
+                &project_path,
+                &environment_config,
+                &variables,
+                &targets,
+                &current_graph,
+                &e,
+            )
+            .await;
+        }
+    };
+    timings.record("Build resource graph", start);
+    let watched_files = if watch {
+        match get_watched_files(project, &project_path, &next_graph) {
             Ok(v) => v,
             Err(e) => {
-                logger::end_action(Paint::red(e));
-                return 1;
+                logger::end_action(Paint::red(report::error(&e)));
+                return fail_deployment(
+                    &project_path,
+                    &environment_config,
+                    &variables,
+                    &targets,
+                    &current_graph,
+                    &e,
+                )
+                .await;
             }
-        };
+        }
+    } else {
+        Vec::new()
+    };
+    report::set_environment(&environment_config.label);
     logger::end_action("Succeeded");
+    let diff = match next_graph.diff(&current_graph) {
+        Ok(v) => v,
+        Err(e) => {
+            logger::log(Paint::red(report::error(&e)));
+            return fail_deployment(
+                &project_path,
+                &environment_config,
+                &variables,
+                &targets,
+                &current_graph,
+                &e,
+            )
+            .await;
+        }
+    };
+    let approved = approve_changes(
+        "deploy",
+        &environment_config,
+        &targets,
+        &current_graph,
+        &diff,
+        yes,
+    );
+    match approved {
+        Ok(true) => {}
+        Ok(false) => {
+            logger::log(Paint::red(report::error("Deployment cancelled")));
+            return 1;
+        }
+        Err(e) => {
+            logger::log(Paint::red(report::error(e)));
+            return 1;
+        }
+    }
 
     logger::start_action("Deploying resources:");
+    let start = Instant::now();
     let mut resource_manager = match RobloxResourceManager::new(&project_path, payment_source).await
     {
         Ok(v) => v,
         Err(e) => {
-            logger::end_action(Paint::red(e));
-            return 1;
+            logger::end_action(Paint::red(report::error(&e)));
+            return fail_deployment(
+                &project_path,
+                &environment_config,
+                &variables,
+                &targets,
+                &current_graph,
+                &e,
+            )
+            .await;
         }
     };
+    timings.record("Authenticate", start);
 
-    let results = next_graph
+    let start = Instant::now();
+    let evaluated = next_graph
         .evaluate(&current_graph, &mut resource_manager, allow_purchases)
         .await;
-    match &results {
-        Ok(results) => {
-            match results {
-                EvaluateResults {
-                    created_count: 0,
-                    updated_count: 0,
-                    deleted_count: 0,
-                    skipped_count: 0,
-                    ..
-                } => logger::end_action("No changes required"),
-                EvaluateResults {
-                    created_count,
-                    updated_count,
-                    deleted_count,
-                    noop_count,
-                    skipped_count,
-                } => logger::end_action(format!(
-                    "Succeeded with {} create(s), {} update(s), {} delete(s), {} noop(s), {} skip(s)",
-                    created_count, updated_count, deleted_count, noop_count, skipped_count
-                )),
-            };
-        }
-        Err(e) => {
-            logger::end_action(Paint::red(e));
-        }
-    };
+    timings.record("Deploy resources", start);
+    if let Ok(evaluated) = &evaluated {
+        report::set_results(evaluated);
+    }
+    let results = evaluated.clone().and_then(|results| results.into_result());
+    log_evaluate_results(&results);
 
-    if environment_config.tag_commit && results.is_ok() {
+    if let (true, Ok(results)) = (environment_config.tag_commit, &results) {
         logger::start_action("Tagging commit:");
+        let start = Instant::now();
         match tag_commit(
-            project_path.clone(),
-            &target_config,
+            &project_path,
+            &environment_config,
+            &targets,
             &next_graph,
             &current_graph,
+            results,
+            push,
         ) {
             Ok(0) => logger::end_action("No tagging required"),
-            Ok(tag_count) => {
+            Ok(tag_count) if push => {
                 logger::end_action(format!("Succeeded in pushing {} tag(s)", tag_count))
             }
-            Err(e) => logger::end_action(Paint::red(e)),
+            Ok(tag_count) => {
+                logger::end_action(format!("Succeeded in creating {} tag(s)", tag_count))
+            }
+            Err(e) => logger::end_action(Paint::red(report::error(e))),
         };
+        timings.record("Tag commit", start);
     }
 
     logger::start_action("Saving state:");
+    let start = Instant::now();
+    record_deployment(&mut state, &environment_config);
     state.environments.insert(
         environment_config.label.clone(),
         next_graph.get_resource_list(),
     );
-    match save_state(&project_path, &state_config, &state).await {
-        Ok(_) => {}
-        Err(e) => {
-            logger::end_action(Paint::red(e));
-            return 1;
+    let saved = save_state(&project_path, &state_config, &state).await;
+    match &saved {
+        Ok(_) => {
+            timings.record("Save state", start);
+            logger::end_action("Succeeded");
+            generate_code(&project_path, &codegen, &environment_config.label, &state);
         }
+        Err(e) => logger::end_action(Paint::red(report::error(e))),
     };
-    logger::end_action("Succeeded");
+    // A deployment whose state could not be saved has failed even if its resources were deployed
+    let error = results.as_ref().err().or(saved.as_ref().err());
+
+    let target_results = get_target_results(&targets, &next_graph);
+    log_target_results(&target_results);
 
-    log_target_results(&target_config, &next_graph);
+    if let Ok(evaluated) = &evaluated {
+        emit_github_annotations(evaluated);
+    }
+    if report_file.is_some() || step_summary {
+        let markdown = get_markdown_report(
+            "deploy",
+            &environment_config.label,
+            &targets,
+            &target_results,
+            &diff,
+            evaluated.as_ref().ok(),
+            error.map(|e| e.as_str()),
+        );
+        if let Err(e) = write_markdown_report(&markdown, report_file, step_summary) {
+            logger::log(Paint::red(report::error(e)));
+        }
+    }
+    report::set_targets(target_results);
+
+    let start = Instant::now();
+    notify_deployment(
+        &project_path,
+        &environment_config,
+        &variables,
+        &targets,
+        &current_graph,
+        &next_graph,
+        evaluated.as_ref().ok(),
+        error.map(|e| e.as_str()),
+    )
+    .await;
+    if !environment_config.notify.is_empty() {
+        timings.record("Send notifications", start);
+    }
+
+    let timings_report = timings.get_report(evaluated.as_ref().ok(), slow_threshold);
+    report::set_timings(&timings_report);
+    if show_timings {
+        log_timings(&timings_report);
+    }
+
+    if saved.is_err() {
+        return 1;
+    }
+
+    if watch {
+        let watcher = Watcher {
+            project,
+            target_label,
+            allow_purchases,
+            project_path,
+            environment_config,
+            targets,
+            state,
+            state_config,
+            current_graph: next_graph,
+            resource_manager,
+            watched_files,
+            codegen,
+        };
+        return watcher.run(debounce).await;
+    }
 
     match &results {
         Ok(_) => 0,
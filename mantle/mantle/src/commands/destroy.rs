@@ -1,21 +1,130 @@
-use std::str;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    str,
+};
 
 use yansi::Paint;
 
+use crate::{approval::approve_changes, commands::diff::print_diff, report};
+
 use rbx_mantle::{
     config::load_project_config,
-    project::{load_project, Project},
-    resource_graph::{EvaluateResults, ResourceGraph},
-    roblox_resource_manager::RobloxResourceManager,
+    project::{load_project, match_patterns, Project},
+    resource_graph::{EvaluateResults, Resource, ResourceGraph, ResourceId},
+    roblox_resource_manager::{RobloxInputs, RobloxOutputs, RobloxResource, RobloxResourceManager},
     state::save_state,
 };
 
-pub async fn run(project: Option<&str>, environment: Option<&str>) -> i32 {
+/// Returns the IDs of the resources and everything reachable from them through the edges.
+fn get_reachable(
+    resource_ids: Vec<ResourceId>,
+    edges: &BTreeMap<ResourceId, Vec<ResourceId>>,
+) -> BTreeSet<ResourceId> {
+    let mut reachable = BTreeSet::new();
+    let mut stack = resource_ids;
+    while let Some(resource_id) = stack.pop() {
+        if !reachable.insert(resource_id.clone()) {
+            continue;
+        }
+        if let Some(next) = edges.get(&resource_id) {
+            stack.extend(next.iter().cloned());
+        }
+    }
+    reachable
+}
+
+/// Returns the IDs of the experience, its configuration and its start place, which are kept by
+/// `--keep-experience`.
+fn get_experience_resource_ids(resources: &[RobloxResource]) -> Vec<ResourceId> {
+    let start_place_ids = resources
+        .iter()
+        .filter(|resource| matches!(resource.get_inputs(), RobloxInputs::Place(inputs) if inputs.is_start))
+        .map(|resource| resource.get_id())
+        .collect::<Vec<_>>();
+
+    resources
+        .iter()
+        .filter(|resource| match resource.get_inputs() {
+            RobloxInputs::Experience(_)
+            | RobloxInputs::ExperienceConfiguration(_)
+            | RobloxInputs::ExperienceActivation(_)
+            | RobloxInputs::ExperienceIcon(_)
+            | RobloxInputs::ExperienceThumbnail(_)
+            | RobloxInputs::ExperienceThumbnailOrder
+            | RobloxInputs::SpatialVoice(_) => true,
+            RobloxInputs::Place(inputs) => inputs.is_start,
+            RobloxInputs::PlaceFile(_) | RobloxInputs::PlaceConfiguration(_) => resource
+                .get_dependencies()
+                .iter()
+                .any(|dependency| start_place_ids.contains(dependency)),
+            _ => false,
+        })
+        .map(|resource| resource.get_id())
+        .collect()
+}
+
+/// Returns the IDs of the resources to destroy. Resources which depend on a destroyed resource are
+/// also destroyed, and resources which a kept resource depends on are also kept.
+fn get_destroyed_resource_ids(
+    graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+    target_patterns: &[String],
+    exclude_patterns: &[String],
+    keep_experience: bool,
+) -> BTreeSet<ResourceId> {
+    let resources = graph.get_resource_list();
+
+    let mut dependencies: BTreeMap<ResourceId, Vec<ResourceId>> = BTreeMap::new();
+    let mut dependents: BTreeMap<ResourceId, Vec<ResourceId>> = BTreeMap::new();
+    for resource in resources.iter() {
+        for dependency in resource.get_dependencies() {
+            dependents
+                .entry(dependency.clone())
+                .or_default()
+                .push(resource.get_id());
+            dependencies
+                .entry(resource.get_id())
+                .or_default()
+                .push(dependency);
+        }
+    }
+
+    let targeted = resources
+        .iter()
+        .map(|resource| resource.get_id())
+        .filter(|resource_id| {
+            target_patterns.is_empty() || match_patterns(resource_id, target_patterns)
+        })
+        .collect();
+    let mut destroyed = get_reachable(targeted, &dependents);
+
+    let mut excluded = resources
+        .iter()
+        .map(|resource| resource.get_id())
+        .filter(|resource_id| match_patterns(resource_id, exclude_patterns))
+        .collect::<Vec<_>>();
+    if keep_experience {
+        excluded.extend(get_experience_resource_ids(&resources));
+    }
+    let kept = get_reachable(excluded, &dependencies);
+
+    destroyed.retain(|resource_id| !kept.contains(resource_id));
+    destroyed
+}
+
+pub async fn run(
+    project: Option<&str>,
+    environment: Option<&str>,
+    yes: bool,
+    dry_run: bool,
+    target_patterns: &[String],
+    exclude_patterns: &[String],
+    keep_experience: bool,
+) -> i32 {
     logger::start_action("Loading project:");
     let (project_path, config) = match load_project_config(project) {
         Ok(v) => v,
         Err(e) => {
-            logger::end_action(Paint::red(e));
+            logger::end_action(Paint::red(report::error(e)));
             return 1;
         }
     };
@@ -23,6 +132,7 @@ pub async fn run(project: Option<&str>, environment: Option<&str>) -> i32 {
         current_graph,
         mut state,
         environment_config,
+        targets,
         payment_source,
         state_config,
         ..
@@ -33,26 +143,102 @@ pub async fn run(project: Option<&str>, environment: Option<&str>) -> i32 {
             return 0;
         }
         Err(e) => {
-            logger::end_action(Paint::red(e));
+            logger::end_action(Paint::red(report::error(e)));
             return 1;
         }
     };
+    report::set_environment(&environment_config.label);
     logger::end_action("Succeeded");
 
+    let destroyed_resource_ids = get_destroyed_resource_ids(
+        &current_graph,
+        target_patterns,
+        exclude_patterns,
+        keep_experience,
+    );
+    let protected_resource_ids = destroyed_resource_ids
+        .iter()
+        .filter(|resource_id| match_patterns(resource_id, &environment_config.protected_resources))
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut next_graph = ResourceGraph::new(
+        &current_graph
+            .get_resource_list()
+            .into_iter()
+            .filter(|resource| !destroyed_resource_ids.contains(&resource.get_id()))
+            .collect::<Vec<_>>(),
+    );
+
+    if dry_run {
+        logger::start_action("Planned changes:");
+        match next_graph.diff(&current_graph) {
+            Ok(diff) => print_diff(&diff),
+            Err(e) => {
+                logger::end_action(Paint::red(report::error(e)));
+                return 1;
+            }
+        }
+        for resource_id in protected_resource_ids.iter() {
+            logger::log(Paint::yellow(format!(
+                "{} is protected and would prevent the environment from being destroyed",
+                resource_id
+            )));
+        }
+        logger::end_action(format!(
+            "Would destroy {} resource(s)",
+            Paint::cyan(destroyed_resource_ids.len())
+        ));
+        report::set_data(&destroyed_resource_ids);
+        return 0;
+    }
+
+    if !protected_resource_ids.is_empty() {
+        logger::log(Paint::red(report::error(format!(
+            "Refusing to destroy protected resource(s): {}",
+            protected_resource_ids.join(", ")
+        ))));
+        return 1;
+    }
+
+    let approved = next_graph.diff(&current_graph).and_then(|diff| {
+        approve_changes(
+            "destroy",
+            &environment_config,
+            &targets,
+            &current_graph,
+            &diff,
+            yes,
+        )
+    });
+    match approved {
+        Ok(true) => {}
+        Ok(false) => {
+            logger::log(Paint::red(report::error("Destroy cancelled")));
+            return 1;
+        }
+        Err(e) => {
+            logger::log(Paint::red(report::error(e)));
+            return 1;
+        }
+    }
+
     logger::start_action("Destroying resources:");
     let mut resource_manager = match RobloxResourceManager::new(&project_path, payment_source).await
     {
         Ok(v) => v,
         Err(e) => {
-            logger::end_action(Paint::red(e));
+            logger::end_action(Paint::red(report::error(e)));
             return 1;
         }
     };
 
-    let mut next_graph = ResourceGraph::new(&Vec::new());
     let results = next_graph
         .evaluate(&current_graph, &mut resource_manager, false)
-        .await;
+        .await
+        .and_then(|results| {
+            report::set_results(&results);
+            results.into_result()
+        });
     match &results {
         Ok(results) => {
             match results {
@@ -65,7 +251,7 @@ pub async fn run(project: Option<&str>, environment: Option<&str>) -> i32 {
             };
         }
         Err(e) => {
-            logger::end_action(Paint::red(e));
+            logger::end_action(Paint::red(report::error(e)));
         }
     };
 
@@ -73,6 +259,7 @@ pub async fn run(project: Option<&str>, environment: Option<&str>) -> i32 {
     let resource_list = next_graph.get_resource_list();
     if resource_list.is_empty() {
         state.environments.remove(&environment_config.label);
+        state.deployed_at.remove(&environment_config.label);
     } else {
         state.environments.insert(
             environment_config.label.clone(),
@@ -82,7 +269,7 @@ pub async fn run(project: Option<&str>, environment: Option<&str>) -> i32 {
     match save_state(&project_path, &state_config, &state).await {
         Ok(_) => {}
         Err(e) => {
-            logger::end_action(Paint::red(e));
+            logger::end_action(Paint::red(report::error(e)));
             return 1;
         }
     };
@@ -93,3 +280,100 @@ pub async fn run(project: Option<&str>, environment: Option<&str>) -> i32 {
         Err(_) => 1,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rbx_mantle::roblox_resource_manager::{
+        ExperienceInputs, FileInputs, PlaceInputs, ProductInputs,
+    };
+
+    use super::*;
+
+    fn get_graph() -> ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs> {
+        let experience = RobloxResource::new(
+            "experience_singleton",
+            RobloxInputs::Experience(ExperienceInputs { group_id: None }),
+            &[],
+        );
+        let start_place = RobloxResource::new(
+            "place_start",
+            RobloxInputs::Place(PlaceInputs { is_start: true }),
+            &[&experience],
+        );
+        let start_place_file = RobloxResource::new(
+            "placeFile_start",
+            RobloxInputs::PlaceFile(FileInputs {
+                file_path: "start.rbxlx".to_owned(),
+                file_hash: "abc".to_owned(),
+            }),
+            &[&start_place],
+        );
+        let lobby_place = RobloxResource::new(
+            "place_lobby",
+            RobloxInputs::Place(PlaceInputs { is_start: false }),
+            &[&experience],
+        );
+        let product = RobloxResource::new(
+            "product_gems",
+            RobloxInputs::Product(ProductInputs {
+                name: "Gems".to_owned(),
+                description: "".to_owned(),
+                price: 100,
+            }),
+            &[&experience],
+        );
+        let product_icon = RobloxResource::new(
+            "productIcon_gems",
+            RobloxInputs::ProductIcon(FileInputs {
+                file_path: "gems.png".to_owned(),
+                file_hash: "def".to_owned(),
+            }),
+            &[&product],
+        );
+        ResourceGraph::new(&[
+            experience,
+            start_place,
+            start_place_file,
+            lobby_place,
+            product,
+            product_icon,
+        ])
+    }
+
+    fn get_ids(resource_ids: &[&str]) -> BTreeSet<ResourceId> {
+        resource_ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn destroys_dependents_of_targeted_resources() {
+        let destroyed =
+            get_destroyed_resource_ids(&get_graph(), &["product_*".to_owned()], &[], false);
+        assert_eq!(destroyed, get_ids(&["productIcon_gems", "product_gems"]));
+    }
+
+    #[test]
+    fn leaves_unrelated_resources() {
+        let destroyed =
+            get_destroyed_resource_ids(&get_graph(), &["place_lobby".to_owned()], &[], false);
+        assert_eq!(destroyed, get_ids(&["place_lobby"]));
+    }
+
+    #[test]
+    fn keeps_dependencies_of_excluded_resources() {
+        let destroyed =
+            get_destroyed_resource_ids(&get_graph(), &[], &["productIcon_*".to_owned()], false);
+        assert_eq!(
+            destroyed,
+            get_ids(&["placeFile_start", "place_lobby", "place_start"])
+        );
+    }
+
+    #[test]
+    fn keeps_experience_and_start_place() {
+        let destroyed = get_destroyed_resource_ids(&get_graph(), &[], &[], true);
+        assert_eq!(
+            destroyed,
+            get_ids(&["place_lobby", "productIcon_gems", "product_gems"])
+        );
+    }
+}
@@ -3,33 +3,41 @@ use std::{fs, str};
 use difference::Changeset;
 use yansi::Paint;
 
+use crate::{
+    markdown_report::{get_markdown_report, write_markdown_report},
+    report::{self, get_target_results},
+};
+
 use rbx_mantle::{
     config::load_project_config,
-    project::{load_project, Project},
-    resource_graph::ResourceGraphDiff,
-    state::get_desired_graph,
+    project::{
+        find_environment, get_environment_targets, load_project, resolve_environments, Project,
+    },
+    resource_graph::{ResourceGraph, ResourceGraphDiff},
+    roblox_resource_manager::{RobloxInputs, RobloxOutputs, RobloxResource},
+    state::{get_desired_graph, get_state, get_target_desired_graph},
 };
 
 fn get_changeset(previous_hash: &str, new_hash: &str) -> Changeset {
     Changeset::new(previous_hash, new_hash, "\n")
 }
 
-fn print_diff(diff: ResourceGraphDiff) {
-    for (resource_id, r) in diff.removals.into_iter() {
+pub fn print_diff(diff: &ResourceGraphDiff) {
+    for (resource_id, r) in diff.removals.iter() {
         logger::start_action(format!("{} Removed {}:", Paint::red("-"), resource_id));
         logger::log("Inputs:");
         logger::log_changeset(get_changeset(&r.previous_inputs_hash, ""));
         logger::end_action_without_message();
     }
 
-    for (resource_id, r) in diff.additions.into_iter() {
+    for (resource_id, r) in diff.additions.iter() {
         logger::start_action(format!("{} Added {}:", Paint::green("+"), resource_id));
         logger::log("Inputs:");
         logger::log_changeset(get_changeset("", &r.current_inputs_hash));
         logger::end_action_without_message();
     }
 
-    for (resource_id, r) in diff.changes.into_iter() {
+    for (resource_id, r) in diff.changes.iter() {
         logger::start_action(format!("{} Changed {}:", Paint::yellow("~"), resource_id));
         logger::log("Inputs:");
         logger::log_changeset(get_changeset(
@@ -39,14 +47,14 @@ fn print_diff(diff: ResourceGraphDiff) {
         logger::end_action_without_message();
     }
 
-    for (resource_id, r) in diff.dependency_changes.into_iter() {
+    for (resource_id, r) in diff.dependency_changes.iter() {
         logger::start_action(format!(
             "{} Dependency Changed {}:",
             Paint::new("○").dimmed(),
             resource_id
         ));
         logger::log("Changed dependencies:");
-        for dependency_id in r.changed_dependencies.into_iter() {
+        for dependency_id in r.changed_dependencies.iter() {
             logger::log(format!(
                 " {} {}",
                 Paint::new("-").dimmed(),
@@ -57,23 +65,182 @@ fn print_diff(diff: ResourceGraphDiff) {
     }
 }
 
+/// Writes the diff in the format to the output file, or prints it when no file is provided.
+fn write_diff(
+    diff: &ResourceGraphDiff,
+    output: Option<&str>,
+    format: Option<&str>,
+) -> Result<(), String> {
+    let diff_string = match format {
+        Some("json") => serde_json::to_string_pretty(diff)
+            .map(|x| x + "\n")
+            .map_err(|e| e.to_string()),
+        Some("yaml") => serde_yaml::to_string(diff).map_err(|e| e.to_string()),
+        Some(format) => Err(format!("Unknown format: {}", format)),
+        None => return Ok(()),
+    }
+    .map_err(|_| "Failed to serialize outputs".to_owned())?;
+
+    if let Some(output) = output {
+        fs::write(output, diff_string)
+            .map_err(|e| format!("Unable to write outputs file: {}\n\t{}", output, e))?;
+    } else if !report::is_json_output() {
+        print!("{}", diff_string);
+    }
+    Ok(())
+}
+
+/// Diffs the stored graphs of two environments by their inputs. The diff shows the changes which
+/// would make the destination environment match the source environment.
+async fn run_environments(
+    project: Option<&str>,
+    from_environment: &str,
+    to_environment: &str,
+    output: Option<&str>,
+    format: Option<&str>,
+    report_file: Option<&str>,
+    step_summary: bool,
+) -> i32 {
+    logger::start_action("Loading project:");
+    let (project_path, config) = match load_project_config(project) {
+        Ok(v) => v,
+        Err(e) => {
+            logger::end_action(Paint::red(report::error(e)));
+            return 1;
+        }
+    };
+    let environments = match resolve_environments(&config.environments) {
+        Ok(v) => v,
+        Err(e) => {
+            logger::end_action(Paint::red(report::error(e)));
+            return 1;
+        }
+    };
+    let mut environment_configs = Vec::new();
+    for label in [from_environment, to_environment] {
+        match find_environment(&environments, label) {
+            Some(environment_config) => environment_configs.push(environment_config),
+            None => {
+                logger::end_action(Paint::red(report::error(format!(
+                    "No environment configuration found with name {}",
+                    label
+                ))));
+                return 1;
+            }
+        }
+    }
+    // The report describes the destination environment's targets
+    let targets =
+        match get_environment_targets(project_path.clone(), &config, &environment_configs[1]) {
+            Ok(v) => v,
+            Err(e) => {
+                logger::end_action(Paint::red(report::error(e)));
+                return 1;
+            }
+        };
+    let state = match get_state(&project_path, &config).await {
+        Ok(v) => v,
+        Err(e) => {
+            logger::end_action(Paint::red(report::error(e)));
+            return 1;
+        }
+    };
+    let get_graph = |label: &str| -> Result<
+        ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+        String,
+    > {
+        match state.environments.get(label) {
+            Some(resources) => Ok(ResourceGraph::new(resources)),
+            None => Err(format!("Environment {} has not been deployed", label)),
+        }
+    };
+    let (mut from_graph, to_graph) = match (get_graph(from_environment), get_graph(to_environment))
+    {
+        (Ok(from_graph), Ok(to_graph)) => (from_graph, to_graph),
+        (Err(e), _) | (_, Err(e)) => {
+            logger::end_action(Paint::red(report::error(e)));
+            return 1;
+        }
+    };
+    report::set_environment(to_environment);
+    logger::end_action("Succeeded");
+
+    logger::start_action(format!(
+        "Diffing environments {} and {}:",
+        Paint::cyan(from_environment),
+        Paint::cyan(to_environment)
+    ));
+    let diff = match from_graph.diff(&to_graph) {
+        Ok(v) => v,
+        Err(e) => {
+            logger::end_action(Paint::red(report::error(e)));
+            return 1;
+        }
+    };
+    print_diff(&diff);
+    report::set_data(&diff);
+    logger::end_action("Succeeded");
+
+    if report_file.is_some() || step_summary {
+        let markdown = get_markdown_report(
+            "diff",
+            &format!("{} → {}", from_environment, to_environment),
+            &targets,
+            &get_target_results(&targets, &to_graph),
+            &diff,
+            None,
+            None,
+        );
+        if let Err(e) = write_markdown_report(&markdown, report_file, step_summary) {
+            logger::log(Paint::red(report::error(e)));
+            return 1;
+        }
+    }
+
+    if let Err(e) = write_diff(&diff, output, format) {
+        logger::log(Paint::red(report::error(e)));
+        return 1;
+    }
+
+    0
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn run(
     project: Option<&str>,
     environment: Option<&str>,
+    target_label: Option<&str>,
+    compared_environments: Option<(&str, &str)>,
     output: Option<&str>,
     format: Option<&str>,
+    report_file: Option<&str>,
+    step_summary: bool,
 ) -> i32 {
+    if let Some((from_environment, to_environment)) = compared_environments {
+        return run_environments(
+            project,
+            from_environment,
+            to_environment,
+            output,
+            format,
+            report_file,
+            step_summary,
+        )
+        .await;
+    }
+
     logger::start_action("Loading project:");
     let (project_path, config) = match load_project_config(project) {
         Ok(v) => v,
         Err(e) => {
-            logger::end_action(Paint::red(e));
+            logger::end_action(Paint::red(report::error(e)));
             return 1;
         }
     };
     let Project {
         current_graph,
-        target_config,
+        environment_config,
+        targets,
         owner_config,
         ..
     } = match load_project(project_path.clone(), config, environment).await {
@@ -83,18 +250,28 @@ pub async fn run(
             return 0;
         }
         Err(e) => {
-            logger::end_action(Paint::red(e));
+            logger::end_action(Paint::red(report::error(e)));
             return 1;
         }
     };
-    let mut next_graph =
-        match get_desired_graph(project_path.as_path(), &target_config, &owner_config) {
-            Ok(v) => v,
-            Err(e) => {
-                logger::end_action(Paint::red(e));
-                return 1;
-            }
-        };
+    let next_graph = match target_label {
+        Some(target_label) => get_target_desired_graph(
+            project_path.as_path(),
+            &targets,
+            target_label,
+            &owner_config,
+            &current_graph,
+        ),
+        None => get_desired_graph(project_path.as_path(), &targets, &owner_config),
+    };
+    let mut next_graph = match next_graph {
+        Ok(v) => v,
+        Err(e) => {
+            logger::end_action(Paint::red(report::error(e)));
+            return 1;
+        }
+    };
+    report::set_environment(&environment_config.label);
     logger::end_action("Succeeded");
 
     logger::start_action("Diffing resource graphs:");
@@ -103,39 +280,35 @@ pub async fn run(
 
     match diff {
         Ok(diff) => {
-            let outputs_string = format.map(|format| match format {
-                "json" => serde_json::to_string_pretty(&diff)
-                    .map(|x| x + "\n")
-                    .map_err(|e| e.to_string()),
-                "yaml" => serde_yaml::to_string(&diff).map_err(|e| e.to_string()),
-                _ => Err(format!("Unknown format: {}", format)),
-            });
-
-            print_diff(diff);
+            print_diff(&diff);
+            report::set_data(&diff);
             logger::end_action("Succeeded");
 
-            if let Some(outputs_string) = outputs_string {
-                if let Ok(outputs_string) = outputs_string {
-                    if let Some(output) = output {
-                        if let Err(e) = fs::write(output, outputs_string).map_err(|e| {
-                            format!("Unable to write outputs file: {}\n\t{}", output, e)
-                        }) {
-                            logger::log(Paint::red(e));
-                            return 1;
-                        }
-                    } else {
-                        print!("{}", outputs_string);
-                    }
-                } else {
-                    logger::log(Paint::red("Failed to serialize outputs"));
+            if report_file.is_some() || step_summary {
+                let markdown = get_markdown_report(
+                    "diff",
+                    &environment_config.label,
+                    &targets,
+                    &get_target_results(&targets, &current_graph),
+                    &diff,
+                    None,
+                    None,
+                );
+                if let Err(e) = write_markdown_report(&markdown, report_file, step_summary) {
+                    logger::log(Paint::red(report::error(e)));
                     return 1;
                 }
             }
 
+            if let Err(e) = write_diff(&diff, output, format) {
+                logger::log(Paint::red(report::error(e)));
+                return 1;
+            }
+
             0
         }
         Err(e) => {
-            logger::end_action(Paint::red(e));
+            logger::end_action(Paint::red(report::error(e)));
             1
         }
     }
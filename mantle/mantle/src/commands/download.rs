@@ -1,5 +1,7 @@
 use yansi::Paint;
 
+use crate::report;
+
 use rbx_mantle::{
     config::{load_project_config, StateConfig},
     state::{get_state, save_state},
@@ -10,20 +12,22 @@ pub async fn run(project: Option<&str>, key: Option<&str>) -> i32 {
     let (project_path, config) = match load_project_config(project) {
         Ok(v) => v,
         Err(e) => {
-            logger::end_action(Paint::red(e));
+            logger::end_action(Paint::red(report::error(e)));
             return 1;
         }
     };
 
     if !matches!(config.state, StateConfig::Remote(_)) {
-        logger::end_action(Paint::red("Project is not configured with remote state"));
+        logger::end_action(Paint::red(report::error(
+            "Project is not configured with remote state",
+        )));
         return 1;
     }
 
     let state = match get_state(&project_path, &config).await {
         Ok(v) => v,
         Err(e) => {
-            logger::end_action(Paint::red(e));
+            logger::end_action(Paint::red(report::error(e)));
             return 1;
         }
     };
@@ -35,7 +39,7 @@ pub async fn run(project: Option<&str>, key: Option<&str>) -> i32 {
     match save_state(&project_path, &state_config, &state).await {
         Ok(_) => {}
         Err(e) => {
-            logger::end_action(Paint::red(e));
+            logger::end_action(Paint::red(report::error(e)));
             return 1;
         }
     };
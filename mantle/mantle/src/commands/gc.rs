@@ -0,0 +1,152 @@
+use std::time::SystemTime;
+
+use serde::Serialize;
+use yansi::Paint;
+
+use crate::{commands::destroy, report};
+
+use rbx_mantle::{
+    config::load_project_config,
+    project::{
+        find_environment, get_branch_environment_label, get_remote_branches,
+        is_ephemeral_environment, resolve_environments,
+    },
+    state::get_state,
+};
+
+const SECONDS_PER_DAY: u64 = 60 * 60 * 24;
+
+#[derive(Serialize)]
+struct StaleEnvironment {
+    environment: String,
+    reason: String,
+}
+
+pub async fn run(
+    project: Option<&str>,
+    remote: &str,
+    older_than: Option<&str>,
+    yes: bool,
+    dry_run: bool,
+) -> i32 {
+    let older_than = match older_than.map(|days| days.parse::<u64>().map_err(|e| (days, e))) {
+        Some(Ok(days)) => Some(days),
+        Some(Err((days, e))) => {
+            logger::log(Paint::red(report::error(format!(
+                "Age {} is invalid: {}",
+                days, e
+            ))));
+            return 1;
+        }
+        None => None,
+    };
+
+    logger::start_action("Loading project:");
+    let (project_path, config) = match load_project_config(project) {
+        Ok(v) => v,
+        Err(e) => {
+            logger::end_action(Paint::red(report::error(e)));
+            return 1;
+        }
+    };
+    let environments = match resolve_environments(&config.environments) {
+        Ok(v) => v,
+        Err(e) => {
+            logger::end_action(Paint::red(report::error(e)));
+            return 1;
+        }
+    };
+    if !environments.iter().any(is_ephemeral_environment) {
+        logger::end_action("No ephemeral environments are configured");
+        return 0;
+    }
+    let state = match get_state(&project_path, &config).await {
+        Ok(v) => v,
+        Err(e) => {
+            logger::end_action(Paint::red(report::error(e)));
+            return 1;
+        }
+    };
+    logger::end_action("Succeeded");
+
+    logger::start_action("Finding stale environments:");
+    let remote_branches = match get_remote_branches(project_path.clone(), remote) {
+        Ok(v) => v,
+        Err(e) => {
+            logger::end_action(Paint::red(report::error(e)));
+            return 1;
+        }
+    };
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+
+    let mut stale_environments = Vec::new();
+    for label in state.environments.keys() {
+        // Only environments created from a templated label are collected
+        let label_template = match find_environment(&environments, label) {
+            Some(environment) => match environment.label_template {
+                Some(v) => v,
+                None => continue,
+            },
+            None => continue,
+        };
+
+        let reason = if !remote_branches
+            .iter()
+            .any(|branch| &get_branch_environment_label(&label_template, branch) == label)
+        {
+            format!("its branch no longer exists on {}", remote)
+        } else {
+            match (older_than, state.deployed_at.get(label)) {
+                (Some(days), Some(deployed_at))
+                    if now.saturating_sub(*deployed_at) >= days.saturating_mul(SECONDS_PER_DAY) =>
+                {
+                    format!(
+                        "it has not been deployed for {} day(s)",
+                        now.saturating_sub(*deployed_at) / SECONDS_PER_DAY
+                    )
+                }
+                _ => continue,
+            }
+        };
+
+        logger::log(format!("{}: {}", Paint::cyan(label), reason));
+        stale_environments.push(StaleEnvironment {
+            environment: label.clone(),
+            reason,
+        });
+    }
+    report::set_data(&stale_environments);
+    if stale_environments.is_empty() {
+        logger::end_action("No stale environments found");
+        return 0;
+    }
+    logger::end_action(format!(
+        "Found {} stale environment(s)",
+        Paint::cyan(stale_environments.len())
+    ));
+
+    if dry_run {
+        return 0;
+    }
+
+    let mut exit_code = 0;
+    for stale_environment in stale_environments.iter() {
+        let result = destroy::run(
+            project,
+            Some(&stale_environment.environment),
+            yes,
+            false,
+            &[],
+            &[],
+            false,
+        )
+        .await;
+        if result != 0 {
+            exit_code = result;
+        }
+    }
+    exit_code
+}
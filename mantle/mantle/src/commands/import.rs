@@ -4,18 +4,38 @@ use rbx_api::{models::AssetId, RobloxApi};
 use rbx_auth::{RobloxCookieStore, RobloxCsrfTokenStore};
 use yansi::Paint;
 
+use crate::report;
+
 use rbx_mantle::{
     config::load_project_config,
+    import_config::import_config,
     project::{load_project, Project},
-    state::{import_graph, save_state},
+    reconciliation::reconcile_imported_graph,
+    resource_graph::{Resource, ResourceGraph},
+    state::{get_desired_graph, import_graph, import_resource, save_state},
 };
 
-pub async fn run(project: Option<&str>, environment: Option<&str>, target_id: &str) -> i32 {
+async fn create_roblox_api() -> Result<RobloxApi, String> {
+    let cookie_store = Arc::new(RobloxCookieStore::new()?);
+    let csrf_token_store = RobloxCsrfTokenStore::new();
+    let roblox_api = RobloxApi::new(cookie_store, csrf_token_store, None)?;
+    roblox_api.validate_auth().await?;
+    Ok(roblox_api)
+}
+
+pub async fn run(
+    project: Option<&str>,
+    environment: Option<&str>,
+    target_id: Option<&str>,
+    resource: Option<(&str, &str)>,
+    write_config: Option<&str>,
+    reconcile: bool,
+) -> i32 {
     logger::start_action("Loading project:");
     let (project_path, config) = match load_project_config(project) {
         Ok(v) => v,
         Err(e) => {
-            logger::end_action(Paint::red(e));
+            logger::end_action(Paint::red(report::error(e)));
             return 1;
         }
     };
@@ -23,7 +43,9 @@ pub async fn run(project: Option<&str>, environment: Option<&str>, target_id: &s
         current_graph,
         mut state,
         environment_config,
+        targets,
         state_config,
+        owner_config,
         ..
     } = match load_project(project_path.clone(), config, environment).await {
         Ok(Some(v)) => v,
@@ -32,61 +54,159 @@ pub async fn run(project: Option<&str>, environment: Option<&str>, target_id: &s
             return 0;
         }
         Err(e) => {
-            logger::end_action(Paint::red(e));
+            logger::end_action(Paint::red(report::error(e)));
             return 1;
         }
     };
+    report::set_environment(&environment_config.label);
 
-    if !current_graph.get_resource_list().is_empty() {
-        logger::end_action("Environment state already exists: no need to import.");
-        return 0;
-    }
+    let imported_graph = if let Some((resource_id, roblox_id)) = resource {
+        let target = match targets
+            .iter()
+            .find(|target| target.label.is_some() && target.owns_resource(resource_id))
+            .or_else(|| targets.iter().find(|target| target.label.is_none()))
+        {
+            Some(v) => v,
+            None => {
+                logger::end_action(Paint::red(report::error(format!(
+                    "Resource ID {} must be prefixed with the label of one of the project's targets (e.g. <target>/{})",
+                    resource_id, resource_id
+                ))));
+                return 1;
+            }
+        };
+        logger::end_action("Succeeded");
 
-    logger::end_action("Succeeded");
+        logger::start_action(format!("Import resource {}:", Paint::cyan(resource_id)));
+        let roblox_api = match create_roblox_api().await {
+            Ok(v) => v,
+            Err(e) => {
+                logger::end_action(Paint::red(report::error(e)));
+                return 1;
+            }
+        };
+        let resources = match import_resource(
+            &roblox_api,
+            &current_graph,
+            target,
+            resource_id,
+            roblox_id,
+        )
+        .await
+        {
+            Ok(v) => v,
+            Err(e) => {
+                logger::end_action(Paint::red(format!("Failed: {}", report::error(e))));
+                return 1;
+            }
+        };
+        for resource in resources.iter() {
+            logger::log(format!("Imported {}", Paint::cyan(resource.get_id())));
+        }
+        logger::end_action("Succeeded");
 
-    let target_id = match target_id.parse::<AssetId>() {
-        Ok(v) => v,
-        Err(e) => {
-            logger::log(Paint::red(format!(
-                "Experience ID {} is invalid: {}",
-                target_id, e
+        let mut resource_list = current_graph.get_resource_list();
+        resource_list.extend(resources);
+        ResourceGraph::new(&resource_list)
+    } else {
+        if targets.iter().any(|target| target.label.is_some()) {
+            logger::end_action(Paint::red(report::error(
+                "Importing is not supported for projects with multiple targets",
             )));
             return 1;
         }
-    };
 
-    logger::start_action("Import target:");
-    let cookie_store = match RobloxCookieStore::new() {
-        Ok(v) => Arc::new(v),
-        Err(e) => {
-            logger::end_action(Paint::red(e));
-            return 1;
-        }
-    };
-    let csrf_token_store = RobloxCsrfTokenStore::new();
-    let roblox_api = match RobloxApi::new(cookie_store, csrf_token_store, None) {
-        Ok(v) => v,
-        Err(e) => {
-            logger::end_action(Paint::red(e));
-            return 1;
-        }
-    };
-    match roblox_api.validate_auth().await {
-        Ok(_) => {}
-        Err(e) => {
-            logger::end_action(Paint::red(e));
-            return 1;
+        if !current_graph.get_resource_list().is_empty() {
+            logger::end_action("Environment state already exists: no need to import.");
+            return 0;
         }
-    };
 
-    let imported_graph = match import_graph(&roblox_api, target_id).await {
-        Ok(v) => v,
-        Err(e) => {
-            logger::end_action(Paint::red(format!("Failed: {}", e)));
-            return 1;
+        logger::end_action("Succeeded");
+
+        let target_id = target_id.unwrap_or_default();
+        let target_id = match target_id.parse::<AssetId>() {
+            Ok(v) => v,
+            Err(e) => {
+                logger::log(Paint::red(report::error(format!(
+                    "Experience ID {} is invalid: {}",
+                    target_id, e
+                ))));
+                return 1;
+            }
+        };
+
+        logger::start_action("Import target:");
+        let roblox_api = match create_roblox_api().await {
+            Ok(v) => v,
+            Err(e) => {
+                logger::end_action(Paint::red(report::error(e)));
+                return 1;
+            }
+        };
+
+        let imported_graph = match import_graph(&roblox_api, target_id).await {
+            Ok(v) => v,
+            Err(e) => {
+                logger::end_action(Paint::red(format!("Failed: {}", report::error(e))));
+                return 1;
+            }
+        };
+        logger::end_action("Succeeded");
+
+        match write_config {
+            Some(config_file) => {
+                logger::start_action("Write config:");
+                match import_config(
+                    &roblox_api,
+                    &project_path,
+                    config_file,
+                    &imported_graph,
+                    &owner_config,
+                )
+                .await
+                {
+                    Ok(v) => {
+                        logger::end_action("Succeeded");
+                        v
+                    }
+                    Err(e) => {
+                        logger::end_action(Paint::red(format!("Failed: {}", report::error(e))));
+                        return 1;
+                    }
+                }
+            }
+            None if reconcile => {
+                logger::start_action("Reconcile with config:");
+                let desired_graph = match get_desired_graph(&project_path, &targets, &owner_config)
+                {
+                    Ok(v) => v,
+                    Err(e) => {
+                        logger::end_action(Paint::red(format!("Failed: {}", report::error(e))));
+                        return 1;
+                    }
+                };
+                match reconcile_imported_graph(&roblox_api, &imported_graph, &desired_graph).await {
+                    Ok(v) => {
+                        logger::end_action("Succeeded");
+                        v
+                    }
+                    Err(e) => {
+                        logger::end_action(Paint::red(format!("Failed: {}", report::error(e))));
+                        return 1;
+                    }
+                }
+            }
+            None => imported_graph,
         }
     };
-    logger::end_action("Succeeded");
+
+    let imported_resource_ids = imported_graph
+        .get_resource_list()
+        .iter()
+        .map(|resource| resource.get_id())
+        .filter(|resource_id| current_graph.get_outputs(resource_id).is_none())
+        .collect::<Vec<_>>();
+    report::set_data(&imported_resource_ids);
 
     logger::start_action("Saving state:");
     state.environments.insert(
@@ -96,7 +216,7 @@ pub async fn run(project: Option<&str>, environment: Option<&str>, target_id: &s
     match save_state(&project_path, &state_config, &state).await {
         Ok(_) => {}
         Err(e) => {
-            logger::end_action(Paint::red(e));
+            logger::end_action(Paint::red(report::error(e)));
             return 1;
         }
     };
@@ -2,6 +2,9 @@ pub mod deploy;
 pub mod destroy;
 pub mod diff;
 pub mod download;
+pub mod gc;
 pub mod import;
 pub mod outputs;
+pub mod promote;
 pub mod upload;
+pub mod validate;
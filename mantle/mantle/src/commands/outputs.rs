@@ -1,57 +1,323 @@
 use std::{collections::BTreeMap, fs};
 
+use serde_json::{Map, Value};
 use yansi::Paint;
 
+use crate::{codegen::get_codegen_module, report};
+
 use rbx_mantle::{
-    config::load_project_config,
+    config::{load_project_config, CodegenFormat},
     project::{load_project, Project},
     resource_graph::Resource,
+    roblox_resource_manager::RobloxOutputs,
 };
 
+type OutputsMap = BTreeMap<String, Option<RobloxOutputs>>;
+
+/// Splits a resource ID such as `hub/place_start` into its target label (`hub`), type (`place`)
+/// and label (`start`).
+pub fn parse_resource_id(resource_id: &str) -> (Option<&str>, &str, &str) {
+    let (target_label, id) = match resource_id.split_once('/') {
+        Some((target_label, id)) => (Some(target_label), id),
+        None => (None, resource_id),
+    };
+    let (resource_type, label) = id.split_once('_').unwrap_or((id, ""));
+    (target_label, resource_type, label)
+}
+
+/// Returns the fields of a resource's outputs, e.g. `{"assetId": 123}` for a place. Resources
+/// whose outputs have no fields return `None`.
+fn get_output_fields(outputs: &Option<RobloxOutputs>) -> Option<Map<String, Value>> {
+    match serde_json::to_value(outputs.as_ref()?).ok()? {
+        // Outputs are serialized as `{"<type>": {<fields>}}`
+        Value::Object(outputs) => match outputs.into_iter().next() {
+            Some((_, Value::Object(fields))) => Some(fields),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Gets a single output from a `<resource>.<field>` path such as `place_start.assetId`.
+fn get_output_value(outputs_map: &OutputsMap, path: &str) -> Result<Value, String> {
+    // Resource IDs can contain dots (e.g. `asset_images/logo.png`) but fields can not
+    let (resource_id, field) = path.rsplit_once('.').ok_or_else(|| {
+        format!(
+            "Invalid output {}: expected the form <resource>.<field>, e.g. place_start.assetId",
+            path
+        )
+    })?;
+
+    let outputs = outputs_map
+        .get(resource_id)
+        .ok_or_else(|| format!("Resource {} does not exist", resource_id))?;
+    match get_output_fields(outputs).and_then(|fields| fields.get(field).cloned()) {
+        Some(Value::Null) | None => Err(format!(
+            "Resource {} does not have an output named {}",
+            resource_id, field
+        )),
+        Some(value) => Ok(value),
+    }
+}
+
+fn format_output_value(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Converts a name such as `placeFile` or `images/logo.png` into `PLACE_FILE` or
+/// `IMAGES_LOGO_PNG`.
+fn to_variable_name(name: &str) -> String {
+    let mut variable_name = String::new();
+    let mut previous: Option<char> = None;
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            if c.is_ascii_uppercase()
+                && matches!(previous, Some(p) if p.is_ascii_lowercase() || p.is_ascii_digit())
+            {
+                variable_name.push('_');
+            }
+            variable_name.push(c.to_ascii_uppercase());
+        } else if !variable_name.is_empty() && !variable_name.ends_with('_') {
+            variable_name.push('_');
+        }
+        previous = Some(c);
+    }
+    variable_name.trim_end_matches('_').to_owned()
+}
+
+/// Flattens the outputs into variables such as `MANTLE_PLACE_START_ASSET_ID`.
+fn get_output_variables(outputs_map: &OutputsMap) -> Vec<(String, String)> {
+    let mut variables = Vec::new();
+    for (resource_id, outputs) in outputs_map.iter() {
+        let fields = match get_output_fields(outputs) {
+            Some(v) => v,
+            None => continue,
+        };
+        let (target_label, resource_type, label) = parse_resource_id(resource_id);
+        let prefix = [
+            Some("mantle"),
+            target_label,
+            Some(resource_type),
+            Some(label),
+        ]
+        .iter()
+        .flatten()
+        .map(|part| to_variable_name(part))
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_");
+        for (field, value) in fields.iter() {
+            if value.is_null() {
+                continue;
+            }
+            variables.push((
+                format!("{}_{}", prefix, to_variable_name(field)),
+                format_output_value(value),
+            ));
+        }
+    }
+    variables
+}
+
+fn is_plain_value(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./:".contains(c))
+}
+
+fn get_env_string(variables: &[(String, String)]) -> String {
+    variables
+        .iter()
+        .map(|(name, value)| {
+            if is_plain_value(value) {
+                format!("export {}={}\n", name, value)
+            } else {
+                format!("export {}='{}'\n", name, value.replace('\'', "'\\''"))
+            }
+        })
+        .collect()
+}
+
+fn get_dotenv_string(variables: &[(String, String)]) -> String {
+    variables
+        .iter()
+        .map(|(name, value)| {
+            if is_plain_value(value) {
+                format!("{}={}\n", name, value)
+            } else {
+                format!(
+                    "{}=\"{}\"\n",
+                    name,
+                    value.replace('\\', "\\\\").replace('"', "\\\"")
+                )
+            }
+        })
+        .collect()
+}
+
+fn rename_field(mut fields: Map<String, Value>, from: &str, to: &str) -> Map<String, Value> {
+    match fields.remove(from) {
+        Some(value) => [(to.to_owned(), value)].into_iter().collect(),
+        None => Map::new(),
+    }
+}
+
+/// Projects the outputs into a map keyed by the labels used in the configuration, e.g.
+/// `places.start.assetId`. Projects with labelled targets are keyed by the target label first.
+fn get_labelled_outputs(outputs_map: &OutputsMap) -> Value {
+    let mut targets: BTreeMap<Option<&str>, Map<String, Value>> = BTreeMap::new();
+    for (resource_id, outputs) in outputs_map.iter() {
+        let fields = match get_output_fields(outputs) {
+            Some(v) => v,
+            None => continue,
+        };
+        let (target_label, resource_type, label) = parse_resource_id(resource_id);
+        let (group, fields) = match resource_type {
+            "experience" => {
+                let target = targets.entry(target_label).or_default();
+                target.insert("experience".to_owned(), Value::Object(fields));
+                continue;
+            }
+            "place" | "placeFile" => ("places", fields),
+            "product" => ("products", fields),
+            "pass" => ("passes", fields),
+            "badge" => ("badges", fields),
+            "asset" => ("assets", fields),
+            // Icons and aliases are merged into the resource they belong to
+            "productIcon" => ("products", rename_field(fields, "assetId", "iconAssetId")),
+            "badgeIcon" => ("badges", rename_field(fields, "assetId", "iconAssetId")),
+            "assetAlias" => ("assets", rename_field(fields, "name", "alias")),
+            _ => continue,
+        };
+
+        let target = targets.entry(target_label).or_default();
+        let group = target
+            .entry(group)
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Value::Object(group) = group {
+            let entry = group
+                .entry(label)
+                .or_insert_with(|| Value::Object(Map::new()));
+            if let Value::Object(entry) = entry {
+                entry.extend(fields);
+            }
+        }
+    }
+
+    if targets.keys().all(Option::is_none) {
+        Value::Object(targets.remove(&None).unwrap_or_default())
+    } else {
+        Value::Object(
+            targets
+                .into_iter()
+                .map(|(label, outputs)| {
+                    (label.unwrap_or_default().to_owned(), Value::Object(outputs))
+                })
+                .collect(),
+        )
+    }
+}
+
 pub async fn run(
     project: Option<&str>,
     environment: Option<&str>,
     output: Option<&str>,
     format: &str,
+    get: Option<&str>,
+    labels: bool,
 ) -> i32 {
     logger::start_action("Load outputs:");
+    if labels && !matches!(format, "json" | "yaml") {
+        logger::end_action(Paint::red(report::error(format!(
+            "Labelled outputs can not be printed in the {} format",
+            format
+        ))));
+        return 1;
+    }
     let (project_path, config) = match load_project_config(project) {
         Ok(v) => v,
         Err(e) => {
-            logger::end_action(Paint::red(e));
+            logger::end_action(Paint::red(report::error(e)));
             return 1;
         }
     };
-    let Project { current_graph, .. } =
-        match load_project(project_path.clone(), config, environment).await {
-            Ok(Some(v)) => v,
-            Ok(None) => {
-                logger::end_action("No outputs available");
-                return 0;
-            }
-            Err(e) => {
-                logger::end_action(Paint::red(e));
-                return 1;
-            }
-        };
+    let Project {
+        current_graph,
+        state,
+        environment_config,
+        ..
+    } = match load_project(project_path.clone(), config, environment).await {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            logger::end_action("No outputs available");
+            return 0;
+        }
+        Err(e) => {
+            logger::end_action(Paint::red(report::error(e)));
+            return 1;
+        }
+    };
+    report::set_environment(&environment_config.label);
 
     let resources = current_graph.get_resource_list();
     let outputs_map = resources
         .iter()
         .map(|r| (r.get_id(), r.get_outputs()))
-        .collect::<BTreeMap<_, _>>();
-
-    let outputs_string = match match format {
-        "json" => serde_json::to_string_pretty(&outputs_map)
-            .map(|x| x + "\n")
-            .map_err(|e| e.to_string()),
-        "yaml" => serde_yaml::to_string(&outputs_map).map_err(|e| e.to_string()),
-        _ => Err(format!("Unknown format: {}", format)),
-    } {
-        Ok(v) => v,
-        Err(e) => {
-            logger::end_action(Paint::red(format!("Failed to serialize outputs: {}", e)));
-            return 1;
+        .collect::<OutputsMap>();
+
+    let outputs_string = if let Some(path) = get {
+        match get_output_value(&outputs_map, path) {
+            Ok(value) => {
+                report::set_data(&value);
+                format_output_value(&value) + "\n"
+            }
+            Err(e) => {
+                logger::end_action(Paint::red(report::error(e)));
+                return 1;
+            }
+        }
+    } else {
+        let labelled_outputs = labels.then(|| get_labelled_outputs(&outputs_map));
+        match &labelled_outputs {
+            Some(labelled_outputs) => report::set_data(labelled_outputs),
+            None => report::set_data(&outputs_map),
+        }
+
+        match match (format, &labelled_outputs) {
+            ("json", Some(outputs)) => serde_json::to_string_pretty(outputs)
+                .map(|x| x + "\n")
+                .map_err(|e| e.to_string()),
+            ("json", None) => serde_json::to_string_pretty(&outputs_map)
+                .map(|x| x + "\n")
+                .map_err(|e| e.to_string()),
+            ("yaml", Some(outputs)) => serde_yaml::to_string(outputs).map_err(|e| e.to_string()),
+            ("yaml", None) => serde_yaml::to_string(&outputs_map).map_err(|e| e.to_string()),
+            ("env", _) => Ok(get_env_string(&get_output_variables(&outputs_map))),
+            ("dotenv", _) => Ok(get_dotenv_string(&get_output_variables(&outputs_map))),
+            ("luau", _) => Ok(get_codegen_module(
+                CodegenFormat::Luau,
+                &environment_config.label,
+                &state,
+            )),
+            ("ts", _) => Ok(get_codegen_module(
+                CodegenFormat::Ts,
+                &environment_config.label,
+                &state,
+            )),
+            _ => Err(format!("Unknown format: {}", format)),
+        } {
+            Ok(v) => v,
+            Err(e) => {
+                logger::end_action(Paint::red(report::error(format!(
+                    "Failed to serialize outputs: {}",
+                    e
+                ))));
+                return 1;
+            }
         }
     };
     logger::end_action("Succeeded");
@@ -60,10 +326,10 @@ pub async fn run(
         if let Err(e) = fs::write(output, outputs_string)
             .map_err(|e| format!("Unable to write outputs file: {}\n\t{}", output, e))
         {
-            logger::log(Paint::red(e));
+            logger::log(Paint::red(report::error(e)));
             return 1;
         }
-    } else {
+    } else if !report::is_json_output() {
         print!("{}", outputs_string);
     }
 
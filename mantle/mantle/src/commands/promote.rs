@@ -0,0 +1,364 @@
+use std::{collections::BTreeMap, env, fs, path::Path, process};
+
+use rbx_api::models::AssetId;
+use yansi::Paint;
+
+use crate::{
+    approval::approve_changes,
+    report::{self, get_target_results},
+};
+
+use rbx_mantle::{
+    config::load_project_config,
+    project::{find_environment, load_project, resolve_environments, Project},
+    resource_graph::{Resource, ResourceGraph},
+    roblox_resource_manager::{
+        FileInputs, RobloxInputs, RobloxOutputs, RobloxResource, RobloxResourceManager,
+    },
+    state::save_state,
+};
+
+/// A place file whose version in one environment will be published to another. Its inputs are the
+/// destination's file path with the source's file hash.
+struct PlacePromotion {
+    resource_id: String,
+    place_id: AssetId,
+    version: u64,
+    inputs: FileInputs,
+}
+
+/// Finds the place files in the source environment which differ from the destination environment.
+fn get_place_promotions(
+    from_graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+    to_graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+) -> Vec<PlacePromotion> {
+    let to_resources = to_graph
+        .get_resource_list()
+        .into_iter()
+        .map(|resource| (resource.get_id(), resource))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut promotions = Vec::new();
+    for resource in from_graph.get_resource_list() {
+        let inputs = match resource.get_inputs() {
+            RobloxInputs::PlaceFile(inputs) => inputs,
+            _ => continue,
+        };
+        let resource_id = resource.get_id();
+
+        let to_resource = match to_resources.get(&resource_id) {
+            None => {
+                logger::log(format!(
+                    "Skipping {}: it does not exist in the destination environment",
+                    Paint::cyan(&resource_id)
+                ));
+                continue;
+            }
+            Some(to_resource) if to_resource.get_inputs_hash() == resource.get_inputs_hash() => {
+                logger::log(format!(
+                    "Skipping {}: it is already up to date",
+                    Paint::cyan(&resource_id)
+                ));
+                continue;
+            }
+            Some(to_resource) => to_resource,
+        };
+        // The destination's config may read the place file from a different path
+        let inputs = match to_resource.get_inputs() {
+            RobloxInputs::PlaceFile(to_inputs) => FileInputs {
+                file_path: to_inputs.file_path,
+                file_hash: inputs.file_hash,
+            },
+            _ => inputs,
+        };
+
+        let place_resource_id = resource_id.replacen("placeFile_", "place_", 1);
+        match (
+            from_graph.get_outputs(&place_resource_id),
+            resource.get_outputs(),
+        ) {
+            (Some(RobloxOutputs::Place(place)), Some(RobloxOutputs::PlaceFile(place_file))) => {
+                promotions.push(PlacePromotion {
+                    resource_id,
+                    place_id: place.asset_id,
+                    version: place_file.version,
+                    inputs,
+                })
+            }
+            _ => logger::log(format!(
+                "Skipping {}: it has not been published in the source environment",
+                Paint::cyan(&resource_id)
+            )),
+        }
+    }
+    promotions
+}
+
+/// Downloads the published version of each place file into the directory. Returns the inputs to
+/// publish each place file with.
+async fn download_place_files(
+    resource_manager: &RobloxResourceManager,
+    directory: &Path,
+    promotions: &[PlacePromotion],
+) -> Result<BTreeMap<String, FileInputs>, String> {
+    let mut inputs = BTreeMap::new();
+    for promotion in promotions {
+        let data = resource_manager
+            .download_asset_version(promotion.place_id, promotion.version)
+            .await?;
+
+        // Roblox may serve a place in a different format than it was uploaded in, so the file's
+        // extension must match its contents for it to be published correctly
+        let file_path = Path::new(&promotion.inputs.file_path);
+        let extension = if data.starts_with(b"<roblox!") {
+            "rbxl"
+        } else {
+            "rbxlx"
+        };
+        let file_path = if file_path.extension().and_then(|e| e.to_str()) == Some(extension) {
+            promotion.inputs.file_path.clone()
+        } else {
+            file_path
+                .with_extension(extension)
+                .to_str()
+                .ok_or(format!("Path was invalid: {}", file_path.display()))?
+                .to_owned()
+        };
+
+        let path = directory.join(&file_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Unable to create directory {}: {}", parent.display(), e))?;
+        }
+        fs::write(&path, &data)
+            .map_err(|e| format!("Unable to write {}: {}", path.display(), e))?;
+        logger::log(format!(
+            "Downloaded version {} of {}",
+            Paint::cyan(promotion.version),
+            Paint::cyan(&promotion.resource_id)
+        ));
+
+        inputs.insert(
+            promotion.resource_id.clone(),
+            FileInputs {
+                file_path,
+                file_hash: promotion.inputs.file_hash.clone(),
+            },
+        );
+    }
+    Ok(inputs)
+}
+
+pub async fn run(
+    project: Option<&str>,
+    from_environment: &str,
+    to_environment: &str,
+    yes: bool,
+) -> i32 {
+    logger::start_action("Loading project:");
+    let (project_path, config) = match load_project_config(project) {
+        Ok(v) => v,
+        Err(e) => {
+            logger::end_action(Paint::red(report::error(e)));
+            return 1;
+        }
+    };
+    match resolve_environments(&config.environments) {
+        Ok(environments) if find_environment(&environments, from_environment).is_some() => {}
+        Ok(_) => {
+            logger::end_action(Paint::red(report::error(format!(
+                "No environment configuration found with name {}",
+                from_environment
+            ))));
+            return 1;
+        }
+        Err(e) => {
+            logger::end_action(Paint::red(report::error(e)));
+            return 1;
+        }
+    }
+    let Project {
+        current_graph,
+        mut state,
+        environment_config,
+        targets,
+        payment_source,
+        state_config,
+        ..
+    } = match load_project(project_path.clone(), config, Some(to_environment)).await {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            logger::end_action("No promotion necessary");
+            return 0;
+        }
+        Err(e) => {
+            logger::end_action(Paint::red(report::error(e)));
+            return 1;
+        }
+    };
+    let from_graph = match state.environments.get(from_environment) {
+        Some(resources) if !resources.is_empty() => ResourceGraph::new(resources),
+        _ => {
+            logger::end_action(Paint::red(report::error(format!(
+                "Environment {} has not been deployed",
+                from_environment
+            ))));
+            return 1;
+        }
+    };
+    report::set_environment(&environment_config.label);
+    logger::end_action("Succeeded");
+
+    logger::start_action(format!(
+        "Finding place files to promote from {} to {}:",
+        Paint::cyan(from_environment),
+        Paint::cyan(to_environment)
+    ));
+    let promotions = get_place_promotions(&from_graph, &current_graph);
+    if promotions.is_empty() {
+        logger::end_action("No promotion necessary");
+        return 0;
+    }
+    logger::end_action(format!("Found {} place file(s)", promotions.len()));
+
+    // The place files are published with the file hashes of the source environment so that the
+    // destination's state records the same file hashes
+    let mut next_graph = ResourceGraph::new(
+        &current_graph
+            .get_resource_list()
+            .into_iter()
+            .map(|resource| {
+                match promotions
+                    .iter()
+                    .find(|promotion| promotion.resource_id == resource.get_id())
+                {
+                    Some(promotion) => {
+                        resource.with_inputs(RobloxInputs::PlaceFile(promotion.inputs.clone()))
+                    }
+                    None => resource,
+                }
+            })
+            .collect::<Vec<_>>(),
+    );
+    let diff = match next_graph.diff(&current_graph) {
+        Ok(v) => v,
+        Err(e) => {
+            logger::log(Paint::red(report::error(e)));
+            return 1;
+        }
+    };
+    match approve_changes(
+        "promote",
+        &environment_config,
+        &targets,
+        &current_graph,
+        &diff,
+        yes,
+    ) {
+        Ok(true) => {}
+        Ok(false) => {
+            logger::log(Paint::red(report::error("Promotion cancelled")));
+            return 1;
+        }
+        Err(e) => {
+            logger::log(Paint::red(report::error(e)));
+            return 1;
+        }
+    }
+
+    logger::start_action("Downloading place files:");
+    let download_path = env::temp_dir().join(format!("mantle-promote-{}", process::id()));
+    // Place files are read relative to the manager's project path, which is the download directory
+    let mut resource_manager =
+        match RobloxResourceManager::new(&download_path, payment_source).await {
+            Ok(v) => v,
+            Err(e) => {
+                logger::end_action(Paint::red(report::error(e)));
+                return 1;
+            }
+        };
+    let place_file_inputs =
+        match download_place_files(&resource_manager, &download_path, &promotions).await {
+            Ok(v) => v,
+            Err(e) => {
+                let _ = fs::remove_dir_all(&download_path);
+                logger::end_action(Paint::red(report::error(e)));
+                return 1;
+            }
+        };
+    logger::end_action("Succeeded");
+
+    // The downloaded files may have been renamed to match their format, so they are published from
+    // their new paths
+    let mut publish_graph = ResourceGraph::new(
+        &next_graph
+            .get_resource_list()
+            .into_iter()
+            .map(|resource| match place_file_inputs.get(&resource.get_id()) {
+                Some(inputs) => resource.with_inputs(RobloxInputs::PlaceFile(inputs.clone())),
+                None => resource,
+            })
+            .collect::<Vec<_>>(),
+    );
+
+    logger::start_action("Publishing place files:");
+    let evaluated = publish_graph
+        .evaluate(&current_graph, &mut resource_manager, false)
+        .await;
+    let _ = fs::remove_dir_all(&download_path);
+    if let Ok(evaluated) = &evaluated {
+        report::set_results(evaluated);
+    }
+    let results = evaluated.and_then(|results| results.into_result());
+    match &results {
+        Ok(results) => logger::end_action(format!(
+            "Succeeded with {} update(s)",
+            results.updated_count
+        )),
+        Err(e) => logger::end_action(Paint::red(report::error(e))),
+    }
+
+    // The state records the paths the destination's config expects so that its next deployment
+    // does not publish the place files again. Place files which failed to publish keep their
+    // previous inputs.
+    let next_graph = ResourceGraph::new(
+        &publish_graph
+            .get_resource_list()
+            .into_iter()
+            .map(|resource| {
+                let promotion = promotions
+                    .iter()
+                    .find(|promotion| promotion.resource_id == resource.get_id());
+                match (promotion, resource.get_inputs()) {
+                    (Some(promotion), RobloxInputs::PlaceFile(inputs))
+                        if inputs.file_hash == promotion.inputs.file_hash =>
+                    {
+                        resource.with_inputs(RobloxInputs::PlaceFile(promotion.inputs.clone()))
+                    }
+                    _ => resource,
+                }
+            })
+            .collect::<Vec<_>>(),
+    );
+
+    logger::start_action("Saving state:");
+    state.environments.insert(
+        environment_config.label.clone(),
+        next_graph.get_resource_list(),
+    );
+    match save_state(&project_path, &state_config, &state).await {
+        Ok(_) => {}
+        Err(e) => {
+            logger::end_action(Paint::red(report::error(e)));
+            return 1;
+        }
+    };
+    logger::end_action("Succeeded");
+
+    report::set_targets(get_target_results(&targets, &next_graph));
+
+    match results {
+        Ok(_) => 0,
+        Err(_) => 1,
+    }
+}
@@ -0,0 +1,85 @@
+use serde::Serialize;
+use yansi::Paint;
+
+use rbx_mantle::{
+    config::load_project_config,
+    validation::{validate_project, ValidationIssue},
+};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ValidationResults {
+    valid: bool,
+    issues: Vec<ValidationIssue>,
+}
+
+fn print_results(issues: Vec<ValidationIssue>, format: Option<&str>) -> Result<(), String> {
+    if let Some(format) = format {
+        let results = ValidationResults {
+            valid: issues.is_empty(),
+            issues,
+        };
+        let results_string = match format {
+            "json" => serde_json::to_string_pretty(&results)
+                .map(|x| x + "\n")
+                .map_err(|e| e.to_string()),
+            _ => Err(format!("Unknown format: {}", format)),
+        }
+        .map_err(|e| format!("Failed to serialize validation results: {}", e))?;
+        print!("{}", results_string);
+    }
+
+    Ok(())
+}
+
+pub async fn run(project: Option<&str>, format: Option<&str>) -> i32 {
+    logger::start_action("Loading project:");
+    let (project_path, config) = match load_project_config(project) {
+        Ok(v) => v,
+        Err(e) => {
+            logger::end_action(Paint::red(&e));
+            let _ = print_results(
+                vec![ValidationIssue {
+                    environment: "".to_owned(),
+                    path: "".to_owned(),
+                    message: e,
+                }],
+                format,
+            );
+            return 1;
+        }
+    };
+    logger::end_action("Succeeded");
+
+    logger::start_action("Validating project:");
+    let issues = validate_project(&project_path, &config);
+    for issue in issues.iter() {
+        logger::log(format!(
+            "{} {} {}: {}",
+            Paint::red("✗"),
+            Paint::cyan(&issue.environment),
+            issue.path,
+            issue.message
+        ));
+    }
+    let issue_count = issues.len();
+    if issue_count == 0 {
+        logger::end_action(format!(
+            "Succeeded with {} environment(s) checked",
+            config.environments.len()
+        ));
+    } else {
+        logger::end_action(Paint::red(format!("Found {} problem(s)", issue_count)));
+    }
+
+    if let Err(e) = print_results(issues, format) {
+        logger::log(Paint::red(e));
+        return 1;
+    }
+
+    if issue_count == 0 {
+        0
+    } else {
+        1
+    }
+}
@@ -1,8 +1,15 @@
 use dotenv::dotenv;
 use log::info;
 
+mod approval;
 mod cli;
+mod codegen;
 mod commands;
+mod markdown_report;
+mod notify;
+mod report;
+mod timings;
+mod workspace;
 
 #[tokio::main]
 async fn main() {
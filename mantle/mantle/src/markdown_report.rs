@@ -0,0 +1,300 @@
+use std::{collections::BTreeMap, env, fs, io::Write};
+
+use difference::{Changeset, Difference};
+
+use rbx_mantle::{
+    project::ProjectTarget,
+    resource_graph::{EvaluateResults, ResourceGraphDiff, ResourceOperation},
+};
+
+use crate::report::{self, TargetResult};
+
+#[derive(Default)]
+struct TypeSummary {
+    creates: u32,
+    updates: u32,
+    deletes: u32,
+    skips: u32,
+    price: u32,
+}
+
+/// Returns the type of a resource from its ID, e.g. `placeFile` for `hub/placeFile_start`.
+fn get_resource_type(resource_id: &str) -> &str {
+    let id = resource_id.rsplit('/').next().unwrap_or(resource_id);
+    id.split('_').next().unwrap_or(id)
+}
+
+fn get_diff_block(previous_hash: &str, current_hash: &str) -> String {
+    let changeset = Changeset::new(previous_hash, current_hash, "\n");
+    let lines = changeset
+        .diffs
+        .iter()
+        .flat_map(|diff| {
+            let (prefix, text) = match diff {
+                Difference::Same(same) => (" ", same),
+                Difference::Add(add) => ("+", add),
+                Difference::Rem(rem) => ("-", rem),
+            };
+            text.split('\n')
+                .map(move |line| format!("{} {}", prefix, line))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    format!("```diff\n{}\n```", lines.join("\n"))
+}
+
+fn get_details(summary: &str, body: &str) -> String {
+    format!(
+        "<details>\n<summary>{}</summary>\n\n{}\n\n</details>",
+        summary, body
+    )
+}
+
+/// Builds a Markdown report of the planned changes to an environment and, after a deployment, the
+/// results of each resource.
+pub fn get_markdown_report(
+    command: &str,
+    environment_label: &str,
+    targets: &[ProjectTarget],
+    target_results: &[TargetResult],
+    diff: &ResourceGraphDiff,
+    results: Option<&EvaluateResults>,
+    error: Option<&str>,
+) -> String {
+    let mut sections = vec![format!("## Mantle {}: `{}`", command, environment_label)];
+    if let Some(error) = error {
+        sections.push(format!("> **Error:** {}", error));
+    }
+
+    // Summarize the results of a deployment, or the planned changes when there are no results
+    let mut type_summaries: BTreeMap<&str, TypeSummary> = BTreeMap::new();
+    match results {
+        Some(results) => {
+            for resource in results.resources.iter() {
+                let summary = type_summaries
+                    .entry(get_resource_type(&resource.resource_id))
+                    .or_default();
+                match resource.operation {
+                    ResourceOperation::Create => summary.creates += 1,
+                    ResourceOperation::Update => summary.updates += 1,
+                    ResourceOperation::Delete => summary.deletes += 1,
+                    ResourceOperation::Skip | ResourceOperation::Fail => summary.skips += 1,
+                    ResourceOperation::Noop => continue,
+                }
+                summary.price += resource.price.unwrap_or_default();
+            }
+        }
+        None => {
+            for resource_id in diff.additions.keys() {
+                type_summaries
+                    .entry(get_resource_type(resource_id))
+                    .or_default()
+                    .creates += 1;
+            }
+            for resource_id in diff.changes.keys().chain(diff.dependency_changes.keys()) {
+                type_summaries
+                    .entry(get_resource_type(resource_id))
+                    .or_default()
+                    .updates += 1;
+            }
+            for resource_id in diff.removals.keys() {
+                type_summaries
+                    .entry(get_resource_type(resource_id))
+                    .or_default()
+                    .deletes += 1;
+            }
+        }
+    }
+    type_summaries.retain(|_, summary| {
+        summary.creates + summary.updates + summary.deletes + summary.skips > 0
+    });
+
+    if type_summaries.is_empty() {
+        sections.push("No changes.".to_owned());
+    } else {
+        let mut table = vec![
+            "| Resource type | Create | Update | Delete | Skip | Robux |".to_owned(),
+            "|---|---|---|---|---|---|".to_owned(),
+        ];
+        for (resource_type, summary) in type_summaries.iter() {
+            table.push(format!(
+                "| `{}` | {} | {} | {} | {} | {} |",
+                resource_type,
+                summary.creates,
+                summary.updates,
+                summary.deletes,
+                summary.skips,
+                summary.price
+            ));
+        }
+        sections.push(table.join("\n"));
+
+        let total_price = type_summaries
+            .values()
+            .map(|summary| summary.price)
+            .sum::<u32>();
+        if total_price > 0 {
+            sections.push(format!("**{} Robux** were spent.", total_price));
+        }
+    }
+
+    // Failures and skips are only known after a deployment
+    let outcomes = results
+        .map(|results| {
+            results
+                .resources
+                .iter()
+                .filter_map(|resource| match (&resource.operation, &resource.message) {
+                    (ResourceOperation::Fail, Some(message)) => Some((
+                        resource.resource_id.as_str(),
+                        format!(" — :x: failed: {}", message),
+                    )),
+                    (ResourceOperation::Skip, Some(message)) => Some((
+                        resource.resource_id.as_str(),
+                        format!(" — :warning: skipped: {}", message),
+                    )),
+                    (_, _) => resource.price.map(|price| {
+                        (resource.resource_id.as_str(), format!(" — {} Robux", price))
+                    }),
+                })
+                .collect::<BTreeMap<_, _>>()
+        })
+        .unwrap_or_default();
+    let get_summary = |symbol: &str, resource_id: &str| {
+        format!(
+            "{} <code>{}</code>{}",
+            symbol,
+            resource_id,
+            outcomes.get(resource_id).cloned().unwrap_or_default()
+        )
+    };
+
+    let mut details = Vec::new();
+    for (resource_id, r) in diff.removals.iter() {
+        details.push(get_details(
+            &get_summary("-", resource_id),
+            &get_diff_block(&r.previous_inputs_hash, ""),
+        ));
+    }
+    for (resource_id, r) in diff.additions.iter() {
+        details.push(get_details(
+            &get_summary("+", resource_id),
+            &get_diff_block("", &r.current_inputs_hash),
+        ));
+    }
+    for (resource_id, r) in diff.changes.iter() {
+        details.push(get_details(
+            &get_summary("~", resource_id),
+            &get_diff_block(&r.previous_inputs_hash, &r.current_inputs_hash),
+        ));
+    }
+    for (resource_id, r) in diff.dependency_changes.iter() {
+        details.push(get_details(
+            &get_summary("○", resource_id),
+            &format!(
+                "Changed dependencies:\n\n{}",
+                r.changed_dependencies
+                    .iter()
+                    .map(|dependency| format!("- `{}`", dependency))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ),
+        ));
+    }
+    if !details.is_empty() {
+        sections.push(format!("### Resources\n\n{}", details.join("\n")));
+    }
+
+    // Link to the places which are affected by the changes
+    let mut place_links = Vec::new();
+    for (target, target_result) in targets.iter().zip(target_results.iter()) {
+        for (label, url) in target_result.place_urls.iter() {
+            let is_affected = ["place", "placeFile", "placeConfiguration"]
+                .iter()
+                .map(|resource_type| target.resource_id(&format!("{}_{}", resource_type, label)))
+                .any(|resource_id| {
+                    diff.additions.contains_key(&resource_id)
+                        || diff.changes.contains_key(&resource_id)
+                        || diff.dependency_changes.contains_key(&resource_id)
+                });
+            if let (true, Some(url)) = (is_affected, url) {
+                let label = match &target.label {
+                    Some(target_label) => format!("{}/{}", target_label, label),
+                    None => label.clone(),
+                };
+                place_links.push(format!("- [{}]({})", label, url));
+            }
+        }
+    }
+    if !place_links.is_empty() {
+        sections.push(format!("### Places\n\n{}", place_links.join("\n")));
+    }
+
+    sections.join("\n\n") + "\n"
+}
+
+/// Writes the report to the file and appends it to the GitHub Actions job summary
+/// (`$GITHUB_STEP_SUMMARY`) when requested.
+pub fn write_markdown_report(
+    markdown: &str,
+    report_file: Option<&str>,
+    step_summary: bool,
+) -> Result<(), String> {
+    if let Some(report_file) = report_file {
+        fs::write(report_file, markdown)
+            .map_err(|e| format!("Unable to write report file: {}\n\t{}", report_file, e))?;
+        logger::log(format!("Wrote report to {}", report_file));
+    }
+
+    if step_summary {
+        let summary_file = env::var("GITHUB_STEP_SUMMARY")
+            .map_err(|_| "The GITHUB_STEP_SUMMARY environment variable is not set".to_owned())?;
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&summary_file)
+            .and_then(|mut file| file.write_all(markdown.as_bytes()))
+            .map_err(|e| format!("Unable to write job summary: {}\n\t{}", summary_file, e))?;
+        logger::log("Wrote report to the job summary");
+    }
+
+    Ok(())
+}
+
+fn escape_annotation(message: &str) -> String {
+    message
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Emits GitHub Actions `::error` and `::warning` annotations for resources which failed or were
+/// skipped when running in GitHub Actions.
+pub fn emit_github_annotations(results: &EvaluateResults) {
+    if env::var("GITHUB_ACTIONS").as_deref() != Ok("true") {
+        return;
+    }
+
+    for resource in results.resources.iter() {
+        let annotation = match (&resource.operation, &resource.message) {
+            (ResourceOperation::Fail, Some(message)) => format!(
+                "::error title=Mantle::Failed to deploy {}: {}",
+                resource.resource_id,
+                escape_annotation(message)
+            ),
+            (ResourceOperation::Skip, Some(message)) => format!(
+                "::warning title=Mantle::Skipped {}: {}",
+                resource.resource_id,
+                escape_annotation(message)
+            ),
+            _ => continue,
+        };
+
+        // Keep stdout reserved for the JSON result document
+        if report::is_json_output() {
+            eprintln!("{}", annotation);
+        } else {
+            println!("{}", annotation);
+        }
+    }
+}
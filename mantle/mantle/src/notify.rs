@@ -0,0 +1,350 @@
+use std::{collections::BTreeMap, path::Path};
+
+use serde::Serialize;
+use serde_json::json;
+use yansi::Paint;
+
+use rbx_api::models::AssetId;
+use rbx_mantle::{
+    config::{EnvironmentConfig, NotifyConfig, TargetConfig},
+    interpolation::InterpolationContext,
+    project::ProjectTarget,
+    resource_graph::{EvaluateResults, ResourceGraph},
+    roblox_resource_manager::{RobloxInputs, RobloxOutputs, RobloxResource},
+};
+
+use crate::report::{get_target_results, TargetResult};
+
+#[derive(Serialize)]
+struct UpdatedPlace {
+    target: Option<String>,
+    label: String,
+    version: u64,
+    url: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CreatedProduct {
+    target: Option<String>,
+    label: String,
+    product_id: AssetId,
+}
+
+/// The JSON payload sent to generic webhooks.
+#[derive(Serialize)]
+struct DeploymentPayload<'a> {
+    environment: &'a str,
+    success: bool,
+    error: Option<&'a str>,
+    results: Option<&'a EvaluateResults>,
+    places: &'a [UpdatedPlace],
+    products: &'a [CreatedProduct],
+    targets: &'a [TargetResult],
+    message: String,
+}
+
+fn get_qualified_label(target_label: &Option<String>, label: &str) -> String {
+    match target_label {
+        Some(target_label) => format!("{}/{}", target_label, label),
+        None => label.to_owned(),
+    }
+}
+
+fn get_updated_places(
+    targets: &[ProjectTarget],
+    target_results: &[TargetResult],
+    previous_graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+    next_graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+) -> Vec<UpdatedPlace> {
+    let mut places = Vec::new();
+    for (target, target_result) in targets.iter().zip(target_results.iter()) {
+        match &target.config {
+            TargetConfig::Experience(target_config) => {
+                let mut labels = target_config
+                    .places
+                    .as_ref()
+                    .unwrap()
+                    .keys()
+                    .collect::<Vec<_>>();
+                labels.sort();
+                for label in labels {
+                    let resource_id = target.resource_id(&format!("placeFile_{}", label));
+                    let version = match (
+                        previous_graph.get_outputs(&resource_id),
+                        next_graph.get_outputs(&resource_id),
+                    ) {
+                        (None, Some(RobloxOutputs::PlaceFile(next))) => next.version,
+                        (
+                            Some(RobloxOutputs::PlaceFile(previous)),
+                            Some(RobloxOutputs::PlaceFile(next)),
+                        ) if next.version != previous.version => next.version,
+                        _ => continue,
+                    };
+                    places.push(UpdatedPlace {
+                        target: target.label.clone(),
+                        label: label.clone(),
+                        version,
+                        url: target_result.place_urls.get(label).cloned().flatten(),
+                    });
+                }
+            }
+        }
+    }
+    places
+}
+
+fn get_created_products(
+    targets: &[ProjectTarget],
+    previous_graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+    next_graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+) -> Vec<CreatedProduct> {
+    let mut products = Vec::new();
+    for target in targets {
+        match &target.config {
+            TargetConfig::Experience(target_config) => {
+                let mut labels = target_config
+                    .products
+                    .as_ref()
+                    .map(|products| products.keys().collect::<Vec<_>>())
+                    .unwrap_or_default();
+                labels.sort();
+                for label in labels {
+                    let resource_id = target.resource_id(&format!("product_{}", label));
+                    if let (None, Some(RobloxOutputs::Product(outputs))) = (
+                        previous_graph.get_outputs(&resource_id),
+                        next_graph.get_outputs(&resource_id),
+                    ) {
+                        products.push(CreatedProduct {
+                            target: target.label.clone(),
+                            label: label.clone(),
+                            product_id: outputs.product_id,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    products
+}
+
+struct MessageValues {
+    environment: String,
+    status: String,
+    summary: String,
+    places: String,
+    products: String,
+    url: String,
+    error: String,
+}
+
+impl MessageValues {
+    fn render(&self, template: &str) -> String {
+        template
+            .replace("{env}", &self.environment)
+            .replace("{status}", &self.status)
+            .replace("{summary}", &self.summary)
+            .replace("{places}", &self.places)
+            .replace("{products}", &self.products)
+            .replace("{url}", &self.url)
+            .replace("{error}", &self.error)
+    }
+
+    fn render_default(&self) -> String {
+        let mut sections = vec![format!(
+            "Deployment to {} {}",
+            self.environment, self.status
+        )];
+        if !self.summary.is_empty() {
+            sections.push(self.summary.clone());
+        }
+        if !self.places.is_empty() {
+            sections.push(format!("Updated places:\n{}", self.places));
+        }
+        if !self.products.is_empty() {
+            sections.push(format!("New products:\n{}", self.products));
+        }
+        if !self.url.is_empty() {
+            sections.push(self.url.clone());
+        }
+        if !self.error.is_empty() {
+            sections.push(self.error.clone());
+        }
+        sections.join("\n")
+    }
+}
+
+async fn send_notification(
+    client: &reqwest::Client,
+    url: &str,
+    body: &serde_json::Value,
+) -> Result<(), String> {
+    client
+        .post(url)
+        .json(body)
+        .send()
+        .await
+        .and_then(|res| res.error_for_status())
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Sends the results of a deployment to each of the environment's `notify` webhooks. Failing to
+/// send a notification does not fail the deployment.
+#[allow(clippy::too_many_arguments)]
+pub async fn notify_deployment(
+    project_path: &Path,
+    environment_config: &EnvironmentConfig,
+    variables: &BTreeMap<String, String>,
+    targets: &[ProjectTarget],
+    previous_graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+    next_graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+    results: Option<&EvaluateResults>,
+    error: Option<&str>,
+) {
+    if environment_config.notify.is_empty() {
+        return;
+    }
+
+    logger::start_action("Sending notifications:");
+
+    let target_results = get_target_results(targets, next_graph);
+    let places = get_updated_places(targets, &target_results, previous_graph, next_graph);
+    let products = get_created_products(targets, previous_graph, next_graph);
+
+    let values = MessageValues {
+        environment: environment_config.label.clone(),
+        status: if error.is_none() {
+            "succeeded"
+        } else {
+            "failed"
+        }
+        .to_owned(),
+        summary: results
+            .map(|results| {
+                format!(
+                    "{} create(s), {} update(s), {} delete(s), {} noop(s), {} skip(s)",
+                    results.created_count,
+                    results.updated_count,
+                    results.deleted_count,
+                    results.noop_count,
+                    results.skipped_count
+                )
+            })
+            .unwrap_or_default(),
+        places: places
+            .iter()
+            .map(|place| {
+                let label = get_qualified_label(&place.target, &place.label);
+                match &place.url {
+                    Some(url) => format!("- {}: version {} ({})", label, place.version, url),
+                    None => format!("- {}: version {}", label, place.version),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        products: products
+            .iter()
+            .map(|product| {
+                format!(
+                    "- {} ({})",
+                    get_qualified_label(&product.target, &product.label),
+                    product.product_id
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        url: target_results
+            .iter()
+            .filter_map(|target_result| target_result.experience_url.clone())
+            .collect::<Vec<_>>()
+            .join(", "),
+        error: error.unwrap_or_default().to_owned(),
+    };
+
+    let mut interpolation_context = match InterpolationContext::new(
+        project_path.to_owned(),
+        &environment_config.label,
+        variables.clone(),
+    ) {
+        Ok(v) => v,
+        Err(e) => {
+            logger::end_action(Paint::red(e));
+            return;
+        }
+    };
+
+    let client = reqwest::Client::new();
+    let mut failures_count = 0;
+    for (index, notify_config) in environment_config.notify.iter().enumerate() {
+        let (name, url, message) = match notify_config {
+            NotifyConfig::Discord { discord, message } => ("Discord", discord, message),
+            NotifyConfig::Slack { slack, message } => ("Slack", slack, message),
+            NotifyConfig::Webhook { webhook, message } => ("webhook", webhook, message),
+        };
+        let message = match message {
+            Some(template) => values.render(template),
+            None => values.render_default(),
+        };
+
+        let body = match notify_config {
+            NotifyConfig::Discord { .. } => json!({ "content": message }),
+            NotifyConfig::Slack { .. } => json!({ "text": message }),
+            NotifyConfig::Webhook { .. } => {
+                let payload = DeploymentPayload {
+                    environment: &environment_config.label,
+                    success: error.is_none(),
+                    error,
+                    results,
+                    places: &places,
+                    products: &products,
+                    targets: &target_results,
+                    message,
+                };
+                match serde_json::to_value(&payload) {
+                    Ok(body) => body,
+                    Err(e) => {
+                        logger::log(Paint::red(format!("Failed to serialize payload: {}", e)));
+                        failures_count += 1;
+                        continue;
+                    }
+                }
+            }
+        };
+
+        let path = format!(
+            "environments.{}.notify[{}]",
+            environment_config.label, index
+        );
+        let url = match interpolation_context
+            .interpolate(serde_yaml::Value::String(url.clone()), &path)
+        {
+            Ok(serde_yaml::Value::String(url)) => url,
+            Ok(_) => url.clone(),
+            Err(e) => {
+                logger::log(Paint::red(e));
+                failures_count += 1;
+                continue;
+            }
+        };
+
+        match send_notification(&client, &url, &body).await {
+            Ok(()) => logger::log(format!("Sent {} notification", Paint::cyan(name))),
+            Err(e) => {
+                logger::log(Paint::red(format!(
+                    "Failed to send {} notification: {}",
+                    name, e
+                )));
+                failures_count += 1;
+            }
+        }
+    }
+
+    if failures_count > 0 {
+        logger::end_action(Paint::red(format!(
+            "Failed to send {} notification(s)",
+            failures_count
+        )));
+    } else {
+        logger::end_action("Succeeded");
+    }
+}
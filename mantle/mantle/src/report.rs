@@ -0,0 +1,341 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fmt::Display,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use serde::Serialize;
+
+use crate::timings::TimingsReport;
+
+use rbx_mantle::{
+    config::TargetConfig,
+    project::ProjectTarget,
+    resource_graph::{EvaluateResults, ResourceGraph},
+    roblox_resource_manager::{RobloxInputs, RobloxOutputs, RobloxResource},
+};
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+static REPORT: Mutex<Report> = Mutex::new(Report::new());
+
+tokio::task_local! {
+    // The report of a project which is run at the same time as other projects
+    static PROJECT_REPORT: RefCell<Report>;
+}
+
+/// The structured result of a command, printed to stdout when running with
+/// `--output json`.
+#[derive(Serialize)]
+struct Report {
+    command: String,
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    environment: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    results: Option<EvaluateResults>,
+    errors: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    targets: Vec<TargetResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timings: Option<TimingsReport>,
+}
+
+impl Report {
+    const fn new() -> Self {
+        Self {
+            command: String::new(),
+            success: false,
+            environment: None,
+            results: None,
+            errors: Vec::new(),
+            targets: Vec::new(),
+            data: None,
+            timings: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct TargetResult {
+    pub label: Option<String>,
+    pub experience_url: Option<String>,
+    pub place_urls: BTreeMap<String, Option<String>>,
+}
+
+fn with_report<F>(f: F)
+where
+    F: FnOnce(&mut Report),
+{
+    if PROJECT_REPORT.try_with(|_| ()).is_ok() {
+        PROJECT_REPORT.with(|report| f(&mut report.borrow_mut()));
+    } else if let Ok(mut report) = REPORT.lock() {
+        f(&mut report);
+    }
+}
+
+/// Runs a project with its own report so that projects run at the same time do not record their
+/// results in each other's reports.
+pub async fn with_project_report<F: Future>(future: F) -> F::Output {
+    PROJECT_REPORT
+        .scope(RefCell::new(Report::new()), future)
+        .await
+}
+
+pub fn set_json_output(enabled: bool) {
+    JSON_OUTPUT.store(enabled, Ordering::SeqCst);
+}
+
+pub fn is_json_output() -> bool {
+    JSON_OUTPUT.load(Ordering::SeqCst)
+}
+
+pub fn set_environment(label: &str) {
+    with_report(|report| report.environment = Some(label.to_owned()));
+}
+
+pub fn set_results(results: &EvaluateResults) {
+    with_report(|report| report.results = Some(results.clone()));
+}
+
+pub fn set_targets(targets: Vec<TargetResult>) {
+    with_report(|report| report.targets = targets);
+}
+
+pub fn set_timings(timings: &TimingsReport) {
+    with_report(|report| report.timings = Some(timings.clone()));
+}
+
+pub fn set_data<T>(data: &T)
+where
+    T: Serialize,
+{
+    let data = serde_json::to_value(data).ok();
+    with_report(|report| report.data = data);
+}
+
+/// Records an error in the report and returns it so that it can also be logged.
+pub fn error<S>(error: S) -> S
+where
+    S: Display,
+{
+    let message = error.to_string();
+    with_report(|report| report.errors.push(message));
+    error
+}
+
+/// The number of resources changed in a project when a command is run for several projects.
+#[derive(Serialize)]
+pub struct ResultCounts {
+    pub created_count: u32,
+    pub updated_count: u32,
+    pub deleted_count: u32,
+    pub failed_count: u32,
+}
+
+/// The results of one project when a command is run for several projects.
+#[derive(Serialize)]
+pub struct ProjectReport {
+    pub project: String,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub results: Option<ResultCounts>,
+    pub errors: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+/// Moves the results of a project out of the report so that the next project starts with an empty
+/// report.
+pub fn take_project_report(project: &str, exit_code: i32) -> ProjectReport {
+    let mut project_report = ProjectReport {
+        project: project.to_owned(),
+        success: exit_code == 0,
+        environment: None,
+        results: None,
+        errors: Vec::new(),
+        data: None,
+    };
+    with_report(|report| {
+        project_report.environment = report.environment.take();
+        project_report.results = report.results.take().map(|results| ResultCounts {
+            created_count: results.created_count,
+            updated_count: results.updated_count,
+            deleted_count: results.deleted_count,
+            failed_count: results.failed_count,
+        });
+        project_report.errors = std::mem::take(&mut report.errors);
+        project_report.data = report.data.take();
+        report.targets.clear();
+        report.timings = None;
+    });
+    project_report
+}
+
+pub fn get_target_results(
+    targets: &[ProjectTarget],
+    graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+) -> Vec<TargetResult> {
+    targets
+        .iter()
+        .map(|target| match &target.config {
+            TargetConfig::Experience(target_config) => {
+                let experience_url =
+                    match graph.get_outputs(&target.resource_id("experience_singleton")) {
+                        Some(RobloxOutputs::Experience(outputs)) => Some(format!(
+                            "https://www.roblox.com/games/{}",
+                            outputs.start_place_id
+                        )),
+                        _ => None,
+                    };
+
+                let place_urls = target_config
+                    .places
+                    .as_ref()
+                    .unwrap()
+                    .keys()
+                    .map(|label| {
+                        let resource_id = target.resource_id(&format!("place_{}", label));
+                        let place_url = match graph.get_outputs(&resource_id) {
+                            Some(RobloxOutputs::Place(outputs)) => {
+                                Some(format!("https://www.roblox.com/games/{}", outputs.asset_id))
+                            }
+                            _ => None,
+                        };
+                        (label.clone(), place_url)
+                    })
+                    .collect();
+
+                TargetResult {
+                    label: target.label.clone(),
+                    experience_url,
+                    place_urls,
+                }
+            }
+        })
+        .collect()
+}
+
+/// Prints the report for the command as a single JSON document when running with
+/// `--output json`.
+pub fn print(command: &str, exit_code: i32) {
+    if !is_json_output() {
+        return;
+    }
+
+    with_report(|report| {
+        report.command = command.to_owned();
+        report.success = exit_code == 0;
+        match serde_json::to_string_pretty(report) {
+            Ok(report_string) => println!("{}", report_string),
+            Err(e) => logger::log(format!("Failed to serialize report: {}", e)),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn get_report_json(command: &str, exit_code: i32) -> serde_json::Value {
+        let mut report_json = serde_json::Value::Null;
+        with_report(|report| {
+            report.command = command.to_owned();
+            report.success = exit_code == 0;
+            report_json = serde_json::to_value(&*report).unwrap();
+        });
+        report_json
+    }
+
+    #[tokio::test]
+    async fn report_omits_empty_fields() {
+        let report_json = with_project_report(async { get_report_json("outputs", 0) }).await;
+
+        assert_eq!(
+            report_json,
+            json!({ "command": "outputs", "success": true, "errors": [] })
+        );
+    }
+
+    #[tokio::test]
+    async fn report_includes_recorded_results() {
+        let report_json = with_project_report(async {
+            set_environment("production");
+            set_results(&EvaluateResults {
+                created_count: 1,
+                failed_count: 1,
+                ..Default::default()
+            });
+            set_data(&json!({ "experienceId": 1 }));
+            error("Unable to create product_gems");
+            get_report_json("deploy", 1)
+        })
+        .await;
+
+        assert_eq!(
+            report_json,
+            json!({
+                "command": "deploy",
+                "success": false,
+                "environment": "production",
+                "results": {
+                    "created_count": 1,
+                    "updated_count": 0,
+                    "deleted_count": 0,
+                    "noop_count": 0,
+                    "skipped_count": 0,
+                    "failed_count": 1,
+                    "resources": []
+                },
+                "errors": ["Unable to create product_gems"],
+                "data": { "experienceId": 1 }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn project_reports_are_taken_from_each_project() {
+        let (first, second) = with_project_report(async {
+            set_environment("production");
+            set_results(&EvaluateResults {
+                updated_count: 2,
+                ..Default::default()
+            });
+            let first = take_project_report("lobby", 0);
+            let second = take_project_report("minigames", 1);
+            (first, second)
+        })
+        .await;
+
+        assert_eq!(
+            serde_json::to_value(first).unwrap(),
+            json!({
+                "project": "lobby",
+                "success": true,
+                "environment": "production",
+                "results": {
+                    "created_count": 0,
+                    "updated_count": 2,
+                    "deleted_count": 0,
+                    "failed_count": 0
+                },
+                "errors": []
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(second).unwrap(),
+            json!({ "project": "minigames", "success": false, "errors": [] })
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use yansi::Paint;
+
+use rbx_mantle::resource_graph::{EvaluateResults, ResourceOperation};
+
+#[derive(Clone, Serialize)]
+pub struct PhaseTiming {
+    pub phase: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ResourceTiming {
+    pub resource_id: String,
+    pub operation: ResourceOperation,
+    pub duration_ms: u64,
+    pub slow: bool,
+}
+
+/// How long each phase of a command and each resource operation took, included in the JSON output.
+#[derive(Clone, Serialize)]
+pub struct TimingsReport {
+    pub total_ms: u64,
+    pub slow_threshold_ms: u64,
+    pub phases: Vec<PhaseTiming>,
+    pub resources: Vec<ResourceTiming>,
+}
+
+/// Records how long each phase of a command takes.
+pub struct Timings {
+    phases: Vec<PhaseTiming>,
+}
+
+fn format_duration(duration_ms: u64) -> String {
+    if duration_ms >= 1000 {
+        format!("{:.2}s", duration_ms as f64 / 1000.0)
+    } else {
+        format!("{}ms", duration_ms)
+    }
+}
+
+impl Timings {
+    pub fn start() -> Self {
+        Self { phases: Vec::new() }
+    }
+
+    /// Records the time elapsed since `start` for the phase.
+    pub fn record(&mut self, phase: &str, start: Instant) {
+        self.phases.push(PhaseTiming {
+            phase: phase.to_owned(),
+            duration_ms: start.elapsed().as_millis() as u64,
+        });
+    }
+
+    /// Returns the phases and the resource operations sorted from slowest to fastest. Resources
+    /// which were not changed are left out.
+    pub fn get_report(
+        &self,
+        results: Option<&EvaluateResults>,
+        slow_threshold: Duration,
+    ) -> TimingsReport {
+        let slow_threshold_ms = slow_threshold.as_millis() as u64;
+
+        let mut phases = self.phases.clone();
+        phases.sort_by(|a, b| b.duration_ms.cmp(&a.duration_ms));
+
+        let mut resources = results
+            .map(|results| {
+                results
+                    .resources
+                    .iter()
+                    .filter(|resource| !matches!(resource.operation, ResourceOperation::Noop))
+                    .map(|resource| ResourceTiming {
+                        resource_id: resource.resource_id.clone(),
+                        operation: resource.operation,
+                        duration_ms: resource.duration_ms,
+                        slow: resource.duration_ms >= slow_threshold_ms,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        resources.sort_by(|a, b| b.duration_ms.cmp(&a.duration_ms));
+
+        TimingsReport {
+            total_ms: self.phases.iter().map(|phase| phase.duration_ms).sum(),
+            slow_threshold_ms,
+            phases,
+            resources,
+        }
+    }
+}
+
+/// Logs the phases and resource operations from slowest to fastest, highlighting resources which
+/// took longer than the slow threshold.
+pub fn log_timings(timings: &TimingsReport) {
+    logger::start_action("Timings:");
+
+    logger::start_action("Phases:");
+    for phase in timings.phases.iter() {
+        logger::log(format!(
+            "{}: {}",
+            phase.phase,
+            Paint::cyan(format_duration(phase.duration_ms))
+        ));
+    }
+    logger::end_action_without_message();
+
+    if !timings.resources.is_empty() {
+        logger::start_action("Resources:");
+        for resource in timings.resources.iter() {
+            let duration = format_duration(resource.duration_ms);
+            if resource.slow {
+                logger::log(format!(
+                    "{}: {} (slow)",
+                    Paint::yellow(&resource.resource_id),
+                    Paint::yellow(duration)
+                ));
+            } else {
+                logger::log(format!(
+                    "{}: {}",
+                    resource.resource_id,
+                    Paint::cyan(duration)
+                ));
+            }
+        }
+        logger::end_action_without_message();
+    }
+
+    let slow_count = timings
+        .resources
+        .iter()
+        .filter(|resource| resource.slow)
+        .count();
+    if slow_count > 0 {
+        logger::end_action(format!(
+            "Total: {} with {} resource(s) slower than {}",
+            format_duration(timings.total_ms),
+            Paint::yellow(slow_count),
+            format_duration(timings.slow_threshold_ms)
+        ));
+    } else {
+        logger::end_action(format!("Total: {}", format_duration(timings.total_ms)));
+    }
+}
@@ -0,0 +1,224 @@
+use std::future::Future;
+
+use clap::ArgMatches;
+use futures::stream::{self, StreamExt};
+use yansi::Paint;
+
+use crate::report::{self, ProjectReport};
+
+use rbx_mantle::config::load_workspace_projects;
+
+/// Options which write to a single file or never finish, so can not be used with several projects.
+const SINGLE_PROJECT_OPTIONS: [(&str, &str); 3] = [
+    ("output_file", "output-file"),
+    ("report", "report"),
+    ("watch", "watch"),
+];
+
+/// Returns the project to run the command for when it is run for a single project.
+fn get_project(matches: &ArgMatches) -> Option<String> {
+    matches.value_of("PROJECT").map(str::to_owned)
+}
+
+/// Returns the projects to run the command for when it is given several projects or a workspace
+/// file, or `None` when it is run for a single project.
+fn get_workspace_projects(matches: &ArgMatches) -> Result<Option<Vec<String>>, String> {
+    let mut projects: Vec<String> = Vec::new();
+    if let Some(values) = matches.values_of("PROJECT") {
+        projects.extend(values.map(str::to_owned));
+    }
+    let workspace_file = matches.value_of("workspace");
+    if let Some(workspace_file) = workspace_file {
+        for project in load_workspace_projects(workspace_file)? {
+            if !projects.contains(&project) {
+                projects.push(project);
+            }
+        }
+    }
+    if workspace_file.is_none() && projects.len() <= 1 {
+        return Ok(None);
+    }
+
+    for (option, flag) in SINGLE_PROJECT_OPTIONS {
+        if matches.is_present(option) {
+            return Err(format!(
+                "The --{} option can not be used with multiple projects",
+                flag
+            ));
+        }
+    }
+
+    Ok(Some(projects))
+}
+
+fn end_project_action(project_report: &ProjectReport) {
+    if project_report.success {
+        logger::end_action("Succeeded");
+    } else {
+        logger::end_action(Paint::red("Failed"));
+    }
+}
+
+async fn run_sequential<F, Fut>(projects: &[String], run: F) -> Vec<ProjectReport>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = i32>,
+{
+    let mut project_reports = Vec::new();
+    for project in projects {
+        logger::start_action(format!("Project {}:", Paint::cyan(project)));
+        let exit_code = run(project.clone()).await;
+        let project_report = report::take_project_report(project, exit_code);
+        end_project_action(&project_report);
+        project_reports.push(project_report);
+    }
+    project_reports
+}
+
+async fn run_parallel<F, Fut>(projects: &[String], jobs: usize, run: F) -> Vec<ProjectReport>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = i32>,
+{
+    // Projects run concurrently in this process so that they share its Roblox session. Each
+    // project's logs are printed once it finishes so that they are not interleaved.
+    let run = &run;
+    let mut project_runs = stream::iter(projects.iter().cloned().enumerate())
+        .map(|(index, project)| {
+            logger::capture(report::with_project_report(async move {
+                let exit_code = run(project.clone()).await;
+                (index, report::take_project_report(&project, exit_code))
+            }))
+        })
+        .buffer_unordered(jobs);
+
+    let mut project_reports = Vec::new();
+    while let Some(((index, project_report), logs)) = project_runs.next().await {
+        logger::start_action(format!("Project {}:", Paint::cyan(&project_report.project)));
+        for line in logs.trim_end().lines() {
+            logger::log(line);
+        }
+        end_project_action(&project_report);
+        project_reports.push((index, project_report));
+    }
+
+    project_reports.sort_by_key(|(index, _)| *index);
+    project_reports
+        .into_iter()
+        .map(|(_, project_report)| project_report)
+        .collect()
+}
+
+fn log_summary(project_reports: &[ProjectReport]) {
+    logger::start_action("Summary:");
+    for project_report in project_reports.iter() {
+        let environment = match &project_report.environment {
+            Some(environment) => format!(" ({})", environment),
+            None => "".to_owned(),
+        };
+        let status = match (&project_report.results, project_report.success) {
+            (Some(results), _) => format!(
+                "{} create(s), {} update(s), {} delete(s), {} failure(s)",
+                results.created_count,
+                results.updated_count,
+                results.deleted_count,
+                results.failed_count
+            ),
+            (None, true) => "Succeeded".to_owned(),
+            (None, false) => "Failed".to_owned(),
+        };
+        let message = format!("{}{}: {}", project_report.project, environment, status);
+        if project_report.success {
+            logger::log(message);
+        } else {
+            logger::log(Paint::red(message));
+            for error in project_report.errors.iter() {
+                logger::log(Paint::red(format!("  {}", error)));
+            }
+        }
+    }
+
+    let failed_count = project_reports
+        .iter()
+        .filter(|project_report| !project_report.success)
+        .count();
+    if failed_count == 0 {
+        logger::end_action(format!(
+            "Succeeded for {} project(s)",
+            project_reports.len()
+        ));
+    } else {
+        logger::end_action(Paint::red(format!(
+            "Failed for {} of {} project(s)",
+            failed_count,
+            project_reports.len()
+        )));
+    }
+}
+
+/// Runs the command for each project and logs a summary of their results. Projects are run one at
+/// a time unless `jobs` allows several projects to run at the same time. Returns a failure exit
+/// code if any project failed.
+async fn run_projects<F, Fut>(projects: &[String], jobs: Option<&str>, run: F) -> i32
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = i32>,
+{
+    let jobs = match jobs.map(|jobs| (jobs, jobs.parse::<usize>())) {
+        None => 1,
+        Some((_, Ok(jobs))) if jobs > 0 => jobs,
+        Some((jobs, Ok(_))) => {
+            logger::log(Paint::red(report::error(format!(
+                "Parallel jobs {} is invalid: must be at least 1",
+                jobs
+            ))));
+            return 1;
+        }
+        Some((jobs, Err(e))) => {
+            logger::log(Paint::red(report::error(format!(
+                "Parallel jobs {} is invalid: {}",
+                jobs, e
+            ))));
+            return 1;
+        }
+    };
+
+    let project_reports = if jobs > 1 {
+        run_parallel(projects, jobs, run).await
+    } else {
+        run_sequential(projects, run).await
+    };
+
+    log_summary(&project_reports);
+    report::set_data(&project_reports);
+
+    if project_reports
+        .iter()
+        .all(|project_report| project_report.success)
+    {
+        0
+    } else {
+        1
+    }
+}
+
+/// Runs the command for each of the projects it was given, or for its only project.
+pub async fn run_for_projects<F, Fut>(matches: &ArgMatches<'_>, run: F) -> i32
+where
+    F: Fn(Option<String>) -> Fut,
+    Fut: Future<Output = i32>,
+{
+    match get_workspace_projects(matches) {
+        Ok(Some(projects)) => {
+            run_projects(&projects, matches.value_of("parallel"), |project| {
+                run(Some(project))
+            })
+            .await
+        }
+        Ok(None) => run(get_project(matches)).await,
+        Err(e) => {
+            logger::log(Paint::red(report::error(e)));
+            1
+        }
+    }
+}
@@ -1,4 +1,4 @@
-mod models;
+pub mod models;
 
 use reqwest::header;
 
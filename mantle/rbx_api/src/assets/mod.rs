@@ -184,4 +184,43 @@ impl RobloxApi {
 
         Ok(())
     }
+
+    pub async fn download_asset(&self, asset_id: AssetId) -> RobloxApiResult<Vec<u8>> {
+        let res = self
+            .csrf_token_store
+            .send_request(|| async {
+                Ok(self
+                    .client
+                    .get("https://assetdelivery.roblox.com/v1/asset/")
+                    .query(&[("id", &asset_id.to_string())]))
+            })
+            .await;
+
+        let response = handle(res).await?;
+
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    pub async fn download_asset_version(
+        &self,
+        asset_id: AssetId,
+        version: u64,
+    ) -> RobloxApiResult<Vec<u8>> {
+        let res = self
+            .csrf_token_store
+            .send_request(|| async {
+                Ok(self
+                    .client
+                    .get("https://assetdelivery.roblox.com/v1/asset/")
+                    .query(&[
+                        ("id", &asset_id.to_string()),
+                        ("version", &version.to_string()),
+                    ]))
+            })
+            .await;
+
+        let response = handle(res).await?;
+
+        Ok(response.bytes().await?.to_vec())
+    }
 }
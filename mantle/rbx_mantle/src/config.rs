@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fmt, fs,
     path::{Path, PathBuf},
     str,
@@ -15,8 +15,12 @@ use rbx_api::{
 };
 use rusoto_core::Region;
 use schemars::JsonSchema;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use url::Url;
+use yaml_rust::{
+    parser::{Event, MarkedEventReceiver, Parser},
+    scanner::Marker,
+};
 use yansi::Paint;
 
 #[derive(JsonSchema, Deserialize)]
@@ -80,7 +84,126 @@ pub struct Config {
     /// target:
     ///   experience: {}
     /// ```
-    pub target: TargetConfig,
+    ///
+    /// Any string in the target may include variables with the format
+    /// `${{ <namespace>.<name> }}` which will be resolved after the
+    /// environment has been selected. See [`variables`](#variables) for more
+    /// information.
+    ///
+    /// This is a shorthand for a project with a single target. Projects which
+    /// deploy multiple targets should use the [`targets`](#targets) property
+    /// instead.
+    #[serde(default)]
+    #[schemars(with = "Option<TargetConfig>")]
+    pub target: serde_yaml::Value,
+
+    /// default({})
+    /// skip_properties()
+    ///
+    /// Defines multiple labelled target resources which Mantle will deploy
+    /// together. Each target has the same shape as the [`target`](#target)
+    /// property and its resources are namespaced by its label in the state
+    /// file (e.g. `hub/place_start`). Cannot be used with the `target` or
+    /// [`include`](#include) properties.
+    ///
+    /// When using `targets`, an environment's
+    /// [`targetOverrides`](#environments--targetoverrides) must be a map from
+    /// target labels to overrides for that target.
+    ///
+    /// ```yml title="Example"
+    /// targets:
+    ///   main:
+    ///     experience:
+    ///       places:
+    ///         start:
+    ///           file: main.rbxl
+    ///   hub:
+    ///     experience:
+    ///       places:
+    ///         start:
+    ///           file: hub.rbxl
+    /// ```
+    #[serde(default)]
+    #[schemars(with = "BTreeMap<String, TargetConfig>")]
+    pub targets: BTreeMap<String, serde_yaml::Value>,
+
+    /// default({})
+    ///
+    /// Variables which can be referenced from the target and the
+    /// environments' [`targetOverrides`](#environments--targetoverrides) with
+    /// the format `${{ variables.<name> }}`. Environments can override
+    /// variables with their own [`variables`](#environments--variables)
+    /// property.
+    ///
+    /// The following variables are also available:
+    ///
+    /// | Variable                   | Description                                     |
+    /// |----------------------------|-------------------------------------------------|
+    /// | `${{ env.<NAME> }}`        | The value of the `<NAME>` environment variable. |
+    /// | `${{ environment.label }}` | The label of the selected environment.          |
+    /// | `${{ git.sha }}`           | The SHA of the current Git commit.              |
+    /// | `${{ git.branch }}`        | The name of the current Git branch.             |
+    ///
+    /// Variables which cannot be resolved are an error. If a value consists of
+    /// only a single variable, the resolved value will be parsed as a number
+    /// or boolean where possible so that it can be used for properties like
+    /// `price`.
+    ///
+    /// ```yml title="Example"
+    /// variables:
+    ///   gemsPrice: 100
+    /// environments:
+    ///   - label: staging
+    ///     variables:
+    ///       gemsPrice: 1
+    ///   - label: production
+    /// target:
+    ///   experience:
+    ///     places:
+    ///       start:
+    ///         file: game.rbxlx
+    ///         configuration:
+    ///           description: 'Build ${{ env.BUILD_NUMBER }} (${{ git.sha }})'
+    ///     products:
+    ///       gems:
+    ///         name: Gems
+    ///         price: ${{ variables.gemsPrice }}
+    /// ```
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+
+    /// default([])
+    ///
+    /// A list of file paths or file globs (relative to this config file) of
+    /// additional YAML files to merge into the target's configuration. Each
+    /// included file has the same shape as the
+    /// [`target.experience`](#target-experience) property, which makes it
+    /// possible to split large configurations (e.g. hundreds of products) into
+    /// multiple files.
+    ///
+    /// Map properties (`places`, `products`, `passes`, `badges` and
+    /// `notifications`) are merged by label, and list properties
+    /// (`thumbnails`, `socialLinks` and `assets`) are appended in the order the
+    /// files are included. Defining the same label or any other property in
+    /// more than one file is an error.
+    ///
+    /// Files can only be included into the [`target`](#target) property, so
+    /// `include` cannot be used with the [`targets`](#targets) property.
+    ///
+    /// ```yml title="Example"
+    /// include:
+    ///   - products/*.yml
+    ///   - badges.yml
+    /// ```
+    ///
+    /// ```yml title="products/gold.yml"
+    /// products:
+    ///   fiftyGold:
+    ///     name: 50 Gold
+    ///     price: 25
+    /// ```
+    #[serde(default)]
+    pub include: Vec<String>,
 
     /// default('local')
     ///
@@ -110,6 +233,46 @@ pub struct Config {
     /// ```
     #[serde(default)]
     pub state: StateConfig,
+
+    /// default([])
+    ///
+    /// Modules to generate from the outputs of each environment after every
+    /// deployment so that the IDs of products, passes, badges and assets can
+    /// be referenced from code, e.g. by syncing the module into a place with
+    /// Rojo. The generated module contains the outputs of every deployed
+    /// environment and selects the one matching the running experience's
+    /// `game.GameId`, falling back to the deployed environment.
+    ///
+    /// ```yml title="Example"
+    /// codegen:
+    ///   - path: src/shared/MantleOutputs.luau
+    ///   - path: src/shared/mantle-outputs.ts
+    /// ```
+    #[serde(default)]
+    pub codegen: Vec<CodegenConfig>,
+}
+
+#[derive(JsonSchema, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct CodegenConfig {
+    /// The path (relative to the project) to write the generated module to.
+    pub path: String,
+
+    /// The language to generate the module in. Defaults to `'luau'` for
+    /// `.lua` and `.luau` files and `'ts'` for `.ts` files.
+    ///
+    /// | Value    | Description                        |
+    /// |----------|------------------------------------|
+    /// | `'luau'` | A typed Luau module.               |
+    /// | `'ts'`   | A TypeScript module for roblox-ts. |
+    pub format: Option<CodegenFormat>,
+}
+
+#[derive(JsonSchema, Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum CodegenFormat {
+    Luau,
+    Ts,
 }
 
 #[derive(JsonSchema, Deserialize, Clone)]
@@ -243,26 +406,158 @@ impl fmt::Display for RemoteStateConfig {
 pub struct EnvironmentConfig {
     /// The label of the environment that is used to identify the environment
     /// via the `--environment` flag. Must be unique across all environments.
+    ///
+    /// Labels containing the `{branch}` placeholder define ephemeral
+    /// environments: each branch matching the environment's
+    /// [`branches`](#environments--branches) gets its own environment, which is
+    /// created on its first deployment. The placeholder is replaced by the
+    /// branch name in lowercase with any characters other than letters and
+    /// digits replaced by `-`, so the branch `feature/New-Shop` deploys to
+    /// `pr-feature-new-shop`. Use `mantle gc` to destroy ephemeral
+    /// environments once their branch has been deleted.
+    ///
+    /// ```yml title="Ephemeral Environment Example"
+    /// environments:
+    ///   - label: production
+    ///     branches: [main]
+    ///   - label: pr-{branch}
+    ///     branches: ['*']
+    ///     targetNamePrefix: environmentLabel
+    ///     targetAccess: private
+    /// ```
     pub label: String,
 
+    /// The label of another environment to inherit configuration from. The
+    /// [`branches`](#environments--branches),
+    /// [`targetNamePrefix`](#environments--targetnameprefix),
+    /// [`targetAccess`](#environments--targetaccess) and
+    /// [`variables`](#environments--variables) properties are inherited
+    /// unless set on this environment, and this environment's
+    /// [`targetOverrides`](#environments--targetoverrides) are applied on top
+    /// of the inherited overrides.
+    ///
+    /// ```yml title="Example"
+    /// environments:
+    ///   - label: staging
+    ///     targetNamePrefix: environmentLabel
+    ///     targetOverrides:
+    ///       configuration:
+    ///         genre: building
+    ///   - label: qa
+    ///     extends: staging
+    ///     targetOverrides:
+    ///       configuration:
+    ///         maxPlayerCount: 10
+    /// ```
+    pub extends: Option<String>,
+
     /// An array of file globs to match against Git branches. If the
-    /// `--environment` flag is not specified, Mantle will pick the first
-    /// environment which contains a matching file glob for the current Git
-    /// branch. If no environments match, Mantle will exit with a success code.
+    /// `--environment` flag and the `MANTLE_ENVIRONMENT` environment variable
+    /// are not specified, Mantle will pick the first environment which contains
+    /// a matching file glob for the current Git branch. If no environments
+    /// match, Mantle will exit with a success code.
+    ///
+    /// On CI providers which check out a detached HEAD, the current branch is
+    /// read from the `GITHUB_HEAD_REF`, `GITHUB_REF_NAME`,
+    /// `CI_COMMIT_REF_NAME` or `BUILDKITE_BRANCH` environment variables.
     #[serde(default)]
     pub branches: Vec<String>,
 
+    /// An array of file globs to match against Git tags which point at the
+    /// current commit. Environments are matched against tags in the same way
+    /// as [`branches`](#environments--branches), which allows deploying from
+    /// CI jobs triggered by tags. Tags never select environments whose label
+    /// contains the `{branch}` placeholder.
+    ///
+    /// ```yml title="Example"
+    /// environments:
+    ///   - label: production
+    ///     tags: [v*]
+    /// ```
+    #[serde(default)]
+    pub tags: Vec<String>,
+
     /// Whether or not to tag the commit with place file versions after
     /// successful deployments. It is recommended to only enable this on your
     /// production environment. Tags will be of the format `<label>-v<version>`
     /// where `<label>` is the label of the place and `<version>` is the place's
-    /// Roblox version.
+    /// Roblox version, unless a [`tagTemplate`](#environments--tagtemplate) is
+    /// specified. Tags are annotated with a summary of the deployment and only
+    /// the new tags are pushed.
     ///
     /// For example, a start place with Roblox version 23 would have the tag
     /// `start-v23`.
     #[serde(default)]
     pub tag_commit: bool,
 
+    /// The format of the tags created by [`tagCommit`](#environments--tagcommit).
+    /// Supports the placeholders `{env}` (the environment's label), `{target}`
+    /// (the target's label when using [`targets`](#targets)), `{label}` (the
+    /// place's label) and `{version}` (the place's Roblox version). Defaults to
+    /// `{label}-v{version}`, or `{target}-{label}-v{version}` when using
+    /// `targets`.
+    ///
+    /// ```yml title="Example"
+    /// environments:
+    ///   - label: production
+    ///     tagCommit: true
+    ///     tagTemplate: '{env}-{label}-v{version}'
+    /// ```
+    pub tag_template: Option<String>,
+
+    /// The git remote to push tags to. Defaults to `origin`.
+    pub tag_remote: Option<String>,
+
+    /// Whether or not the environment requires explicit approval before it
+    /// is deployed to or destroyed. Mantle always asks for confirmation when
+    /// run interactively, but non-interactive runs (e.g. on CI) against a
+    /// protected environment fail unless the `--yes` flag is provided.
+    /// Protected environments must be confirmed by typing their label.
+    ///
+    /// ```yml title="Example"
+    /// environments:
+    ///   - label: production
+    ///     branches: [main]
+    ///     protected: true
+    /// ```
+    #[serde(default)]
+    pub protected: bool,
+
+    /// Glob patterns matching the IDs of resources which must never be
+    /// removed by `mantle destroy`, such as `experience_singleton` or
+    /// `product_*`. Destroying the environment fails if it would remove any
+    /// matching resource; use the `--exclude` or `--keep-experience` flags to
+    /// leave them in place.
+    ///
+    /// ```yml title="Example"
+    /// environments:
+    ///   - label: production
+    ///     protectedResources:
+    ///       - experience_singleton
+    ///       - place_start
+    /// ```
+    #[serde(default)]
+    pub protected_resources: Vec<String>,
+
+    /// skip_properties()
+    ///
+    /// Overrides the project's [`owner`](#owner) for this environment. Accepts
+    /// the same values as the project's `owner` property.
+    ///
+    /// ```yml title="Example"
+    /// owner: personal
+    /// environments:
+    ///   - label: staging
+    ///   - label: production
+    ///     owner:
+    ///       group: 5723117
+    /// ```
+    pub owner: Option<OwnerConfig>,
+
+    /// Overrides the project's [`payments`](#payments) for this environment.
+    /// Accepts the same values as the project's `payments` property.
+    pub payments: Option<PaymentsConfig>,
+
     /// skip_properties()
     ///
     /// Adds a prefix to the target's name configuration. The implementation is dependent on the
@@ -310,8 +605,102 @@ pub struct EnvironmentConfig {
     /// [`targetAccess`](#environments--targetaccess) properties.
     ///
     /// Override the target configuration. Should match the type of the target
-    /// configuration.
-    pub target_overrides: Option<TargetOverridesConfig>,
+    /// configuration. Maps are merged with the target and all other values
+    /// replace the target's values, except for the following:
+    ///
+    /// | Value                          | Description                                                          |
+    /// |--------------------------------|----------------------------------------------------------------------|
+    /// | `<key>: ~delete`               | Removes `<key>` from the target.                                     |
+    /// | `remove: [<key>, ...]`         | Removes each of the listed keys from the target.                     |
+    /// | `<list>: { append: [...] }`    | Adds the items to the end of the target's list.                      |
+    /// | `<list>: { prepend: [...] }`   | Adds the items to the start of the target's list.                    |
+    /// | `<list>: { replace: [...] }`   | Replaces the target's list (the same as providing a list directly).  |
+    ///
+    /// ```yml title="Example"
+    /// environments:
+    ///   - label: staging
+    ///     targetOverrides:
+    ///       products:
+    ///         vipOnly: ~delete
+    ///       passes:
+    ///         remove: [earlyAccess, betaTester]
+    ///       thumbnails:
+    ///         append:
+    ///           - marketing/staging-thumbnail.png
+    /// ```
+    #[schemars(with = "Option<TargetOverridesConfig>")]
+    pub target_overrides: Option<serde_yaml::Value>,
+
+    /// The target overrides of the environments this environment extends,
+    /// starting with the furthest ancestor.
+    #[serde(skip)]
+    pub inherited_target_overrides: Vec<(String, serde_yaml::Value)>,
+
+    /// The templated label of the ephemeral environment this environment was
+    /// created from, e.g. `pr-{branch}`.
+    #[serde(skip)]
+    pub label_template: Option<String>,
+
+    /// default({})
+    ///
+    /// Environment-specific values for the project's
+    /// [`variables`](#variables). Values defined here take precedence over the
+    /// project's variables.
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+
+    /// default([])
+    ///
+    /// Webhooks to notify after each deployment to this environment, whether
+    /// it succeeded or failed.
+    ///
+    /// ```yml title="Example"
+    /// environments:
+    ///   - label: production
+    ///     notify:
+    ///       - discord: ${{ env.DISCORD_WEBHOOK_URL }}
+    ///       - slack: ${{ env.SLACK_WEBHOOK_URL }}
+    ///         message: '{env} deploy {status}: {url}'
+    ///       - webhook: https://example.com/mantle
+    /// ```
+    #[serde(default)]
+    pub notify: Vec<NotifyConfig>,
+}
+
+/// A webhook to notify, which must specify exactly one of `discord`, `slack`
+/// or `webhook`. Each may set a `message` to send, which supports the
+/// placeholders `{env}` (the environment's label), `{status}` (`succeeded` or
+/// `failed`), `{summary}` (the number of created, updated and deleted
+/// resources), `{places}` (the updated places and their versions),
+/// `{products}` (the created products), `{url}` (the experience's URL) and
+/// `{error}`. Defaults to a message including all of them.
+#[derive(JsonSchema, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", untagged, deny_unknown_fields)]
+pub enum NotifyConfig {
+    Discord {
+        /// The URL of a Discord webhook to post a message to. May reference
+        /// environment variables with the format `${{ env.<name> }}` so that
+        /// the URL is not committed.
+        discord: String,
+        message: Option<String>,
+    },
+    Slack {
+        /// The URL of a Slack incoming webhook to post a message to. May
+        /// reference environment variables with the format
+        /// `${{ env.<name> }}`.
+        slack: String,
+        message: Option<String>,
+    },
+    Webhook {
+        /// The URL to post a JSON payload describing the deployment to. The
+        /// payload includes the environment's label, whether the deployment
+        /// succeeded, the results of each resource, the updated places with
+        /// their new versions, the created products and the experience's URL.
+        /// May reference environment variables with the format
+        /// `${{ env.<name> }}`.
+        webhook: String,
+        message: Option<String>,
+    },
 }
 
 #[derive(JsonSchema, Deserialize, Clone)]
@@ -937,6 +1326,123 @@ pub struct ExperienceTargetConfigurationConfig {
     pub avatar_asset_overrides: Option<AvatarAssetOverridesTargetConfig>,
 }
 
+impl From<&ExperienceConfigurationModel> for ExperienceTargetConfigurationConfig {
+    fn from(model: &ExperienceConfigurationModel) -> Self {
+        fn get_constraint(min: &str, max: &str) -> Option<Constraint> {
+            Some(Constraint {
+                min: min.parse().ok(),
+                max: max.parse().ok(),
+            })
+        }
+
+        let min_scales = &model.universe_avatar_min_scales;
+        let max_scales = &model.universe_avatar_max_scales;
+
+        let mut avatar_asset_overrides = AvatarAssetOverridesTargetConfig {
+            face: None,
+            head: None,
+            torso: None,
+            left_arm: None,
+            right_arm: None,
+            left_leg: None,
+            right_leg: None,
+            t_shirt: None,
+            shirt: None,
+            pants: None,
+        };
+        for override_model in model.universe_avatar_asset_overrides.iter() {
+            if override_model.is_player_choice {
+                continue;
+            }
+            let asset_id = override_model.asset_id;
+            match override_model.asset_type_id {
+                AssetTypeId::Face => avatar_asset_overrides.face = asset_id,
+                AssetTypeId::Head => avatar_asset_overrides.head = asset_id,
+                AssetTypeId::Torso => avatar_asset_overrides.torso = asset_id,
+                AssetTypeId::LeftArm => avatar_asset_overrides.left_arm = asset_id,
+                AssetTypeId::RightArm => avatar_asset_overrides.right_arm = asset_id,
+                AssetTypeId::LeftLeg => avatar_asset_overrides.left_leg = asset_id,
+                AssetTypeId::RightLeg => avatar_asset_overrides.right_leg = asset_id,
+                AssetTypeId::TShirt => avatar_asset_overrides.t_shirt = asset_id,
+                AssetTypeId::Shirt => avatar_asset_overrides.shirt = asset_id,
+                AssetTypeId::Pants => avatar_asset_overrides.pants = asset_id,
+                _ => {}
+            }
+        }
+
+        ExperienceTargetConfigurationConfig {
+            genre: Some(match model.genre {
+                ExperienceGenre::All => GenreTargetConfig::All,
+                ExperienceGenre::Adventure => GenreTargetConfig::Adventure,
+                ExperienceGenre::Tutorial => GenreTargetConfig::Building,
+                ExperienceGenre::Funny => GenreTargetConfig::Comedy,
+                ExperienceGenre::Ninja => GenreTargetConfig::Fighting,
+                ExperienceGenre::Fps => GenreTargetConfig::Fps,
+                ExperienceGenre::Scary => GenreTargetConfig::Horror,
+                ExperienceGenre::Fantasy => GenreTargetConfig::Medieval,
+                ExperienceGenre::War => GenreTargetConfig::Military,
+                ExperienceGenre::Pirate => GenreTargetConfig::Naval,
+                ExperienceGenre::Rpg => GenreTargetConfig::Rpg,
+                ExperienceGenre::SciFi => GenreTargetConfig::SciFi,
+                ExperienceGenre::Sports => GenreTargetConfig::Sports,
+                ExperienceGenre::TownAndCity => GenreTargetConfig::TownAndCity,
+                ExperienceGenre::WildWest => GenreTargetConfig::Western,
+            }),
+            playable_devices: Some(
+                model
+                    .playable_devices
+                    .iter()
+                    .map(|device| match device {
+                        ExperiencePlayableDevice::Computer => PlayableDeviceTargetConfig::Computer,
+                        ExperiencePlayableDevice::Phone => PlayableDeviceTargetConfig::Phone,
+                        ExperiencePlayableDevice::Tablet => PlayableDeviceTargetConfig::Tablet,
+                        ExperiencePlayableDevice::Console => PlayableDeviceTargetConfig::Console,
+                        ExperiencePlayableDevice::VR => PlayableDeviceTargetConfig::VR,
+                    })
+                    .collect(),
+            ),
+            playability: Some(match model.is_friends_only {
+                Some(true) => PlayabilityTargetConfig::Friends,
+                Some(false) => PlayabilityTargetConfig::Public,
+                None => PlayabilityTargetConfig::Private,
+            }),
+            paid_access: match (model.is_for_sale, model.price) {
+                (true, Some(price)) => PaidAccessTargetConfig::Price(price),
+                _ => PaidAccessTargetConfig::Disabled,
+            },
+            private_servers: match (model.allow_private_servers, model.private_server_price) {
+                (true, Some(0) | None) => PrivateServersTargetConfig::Free,
+                (true, Some(price)) => PrivateServersTargetConfig::Price(price),
+                (false, _) => PrivateServersTargetConfig::Disabled,
+            },
+            enable_studio_access_to_apis: Some(model.studio_access_to_apis_allowed),
+            allow_third_party_sales: Some(model.permissions.is_third_party_purchase_allowed),
+            allow_third_party_teleports: Some(model.permissions.is_third_party_teleport_allowed),
+            avatar_type: Some(match model.universe_avatar_type {
+                ExperienceAvatarType::MorphToR6 => AvatarTypeTargetConfig::R6,
+                ExperienceAvatarType::MorphToR15 => AvatarTypeTargetConfig::R15,
+                ExperienceAvatarType::PlayerChoice => AvatarTypeTargetConfig::PlayerChoice,
+            }),
+            avatar_animation_type: Some(match model.universe_animation_type {
+                ExperienceAnimationType::Standard => AnimationTypeTargetConfig::Standard,
+                ExperienceAnimationType::PlayerChoice => AnimationTypeTargetConfig::PlayerChoice,
+            }),
+            avatar_collision_type: Some(match model.universe_collision_type {
+                ExperienceCollisionType::OuterBox => CollisionTypeTargetConfig::OuterBox,
+                ExperienceCollisionType::InnerBox => CollisionTypeTargetConfig::InnerBox,
+            }),
+            avatar_scale_constraints: Some(AvatarScaleConstraintsTargetConfig {
+                height: get_constraint(&min_scales.height, &max_scales.height),
+                width: get_constraint(&min_scales.width, &max_scales.width),
+                head: get_constraint(&min_scales.head, &max_scales.head),
+                body_type: get_constraint(&min_scales.body_type, &max_scales.body_type),
+                proportions: get_constraint(&min_scales.proportion, &max_scales.proportion),
+            }),
+            avatar_asset_overrides: Some(avatar_asset_overrides),
+        }
+    }
+}
+
 impl From<&ExperienceTargetConfigurationConfig> for ExperienceConfigurationModel {
     fn from(config: &ExperienceTargetConfigurationConfig) -> Self {
         let mut model = ExperienceConfigurationModel::default();
@@ -1154,6 +1660,24 @@ pub struct PlaceTargetConfigurationConfig {
     pub server_fill: Option<ServerFillTargetConfig>,
 }
 
+impl From<&PlaceConfigurationModel> for PlaceTargetConfigurationConfig {
+    fn from(model: &PlaceConfigurationModel) -> Self {
+        PlaceTargetConfigurationConfig {
+            name: Some(model.name.clone()),
+            description: Some(model.description.clone()),
+            max_player_count: Some(model.max_player_count),
+            allow_copying: Some(model.allow_copying),
+            server_fill: Some(match model.social_slot_type {
+                SocialSlotType::Automatic => ServerFillTargetConfig::RobloxOptimized,
+                SocialSlotType::Empty => ServerFillTargetConfig::Maximum,
+                SocialSlotType::Custom => ServerFillTargetConfig::ReservedSlots(
+                    model.custom_social_slots_count.unwrap_or(0),
+                ),
+            }),
+        }
+    }
+}
+
 impl From<PlaceTargetConfigurationConfig> for PlaceConfigurationModel {
     fn from(config: PlaceTargetConfigurationConfig) -> Self {
         let mut model = PlaceConfigurationModel::default();
@@ -1203,22 +1727,375 @@ fn parse_project_path(project: Option<&str>) -> Result<(PathBuf, PathBuf), Strin
     Err(format!("Config file {} not found", config_file.display()))
 }
 
+/// A container which is being parsed by a [`LineIndexer`]. Mappings hold the key whose value is
+/// being parsed, and sequences hold the index of their next item.
+enum YamlContainer {
+    Mapping(Option<String>),
+    Sequence(usize),
+}
+
+/// Records the line of each key and sequence item of a YAML document by its path, e.g.
+/// `["target", "experience", "products", "gold"]` or `["include", "0"]`.
+#[derive(Default)]
+struct LineIndexer {
+    containers: Vec<YamlContainer>,
+    path: Vec<String>,
+    lines: HashMap<Vec<String>, usize>,
+}
+
+impl LineIndexer {
+    /// Starts a node, returning whether the node is the key of a mapping entry.
+    fn start_node(&mut self, key: Option<&str>, line: usize) -> bool {
+        match self.containers.last_mut() {
+            Some(YamlContainer::Mapping(pending_key @ None)) => {
+                let key = key.unwrap_or_default().to_owned();
+                let mut path = self.path.clone();
+                path.push(key.clone());
+                self.lines.entry(path).or_insert(line);
+                *pending_key = Some(key);
+                true
+            }
+            Some(YamlContainer::Mapping(Some(key))) => {
+                self.path.push(key.clone());
+                false
+            }
+            Some(YamlContainer::Sequence(index)) => {
+                self.path.push(index.to_string());
+                *index += 1;
+                self.lines.entry(self.path.clone()).or_insert(line);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Adds a scalar node, which is either the key of a mapping entry or a complete value.
+    fn add_scalar(&mut self, key: Option<&str>, line: usize) {
+        if !self.start_node(key, line) {
+            self.end_node();
+        }
+    }
+
+    /// Ends a node which was not the key of a mapping entry.
+    fn end_node(&mut self) {
+        match self.containers.last_mut() {
+            Some(YamlContainer::Mapping(pending_key)) => {
+                *pending_key = None;
+                self.path.pop();
+            }
+            Some(YamlContainer::Sequence(_)) => {
+                self.path.pop();
+            }
+            None => {}
+        }
+    }
+}
+
+impl MarkedEventReceiver for LineIndexer {
+    fn on_event(&mut self, event: Event, marker: Marker) {
+        match event {
+            Event::Scalar(value, ..) => self.add_scalar(Some(&value), marker.line()),
+            Event::Alias(_) => self.add_scalar(None, marker.line()),
+            Event::SequenceStart(_) => {
+                self.start_node(None, marker.line());
+                self.containers.push(YamlContainer::Sequence(0));
+            }
+            Event::MappingStart(_) => {
+                self.start_node(None, marker.line());
+                self.containers.push(YamlContainer::Mapping(None));
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                self.containers.pop();
+                self.end_node();
+            }
+            _ => {}
+        }
+    }
+}
+
+struct ConfigFile {
+    path: PathBuf,
+    data: String,
+    lines: HashMap<Vec<String>, usize>,
+}
+
+impl ConfigFile {
+    fn new(path: &Path, data: String) -> Self {
+        // Invalid YAML is reported when the file is parsed, so its lines are only indexed up to
+        // the error
+        let mut indexer = LineIndexer::default();
+        let _ = Parser::new(data.chars()).load(&mut indexer, false);
+
+        Self {
+            path: path.to_owned(),
+            data,
+            lines: indexer.lines,
+        }
+    }
+
+    fn read(path: &Path) -> Result<Self, String> {
+        let data = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read config file: {}\n\t{}", path.display(), e))?;
+        Ok(Self::new(path, data))
+    }
+
+    fn parse<T>(&self) -> Result<T, String>
+    where
+        T: DeserializeOwned,
+    {
+        serde_yaml::from_str::<T>(&self.data).map_err(|e| {
+            format!(
+                "Unable to parse config file {}\n\t{}",
+                self.path.display(),
+                e
+            )
+        })
+    }
+
+    /// Returns a `<file>:<line>` string pointing to the key or sequence item at `path`, or just
+    /// the file if it could not be found.
+    fn location_of(&self, path: &[&str]) -> String {
+        let path = path.iter().map(|key| key.to_string()).collect::<Vec<_>>();
+        match self.lines.get(&path) {
+            Some(line) => format!("{}:{}", self.path.display(), line),
+            None => self.path.display().to_string(),
+        }
+    }
+}
+
+/// Properties of an experience target which are merged by label when included from multiple files.
+const INCLUDE_MAP_PROPERTIES: &[&str] =
+    &["places", "products", "passes", "badges", "notifications"];
+
+/// Properties of an experience target which are appended to when included from multiple files.
+const INCLUDE_LIST_PROPERTIES: &[&str] = &["thumbnails", "socialLinks", "assets"];
+
+/// Properties of an experience target which can only be defined in a single file.
+const INCLUDE_VALUE_PROPERTIES: &[&str] = &["configuration", "icon", "spatialVoice"];
+
+struct IncludeMerger<'a> {
+    config_file: &'a ConfigFile,
+    origins: HashMap<String, String>,
+}
+
+impl<'a> IncludeMerger<'a> {
+    fn new(config_file: &'a ConfigFile) -> Self {
+        Self {
+            config_file,
+            origins: HashMap::new(),
+        }
+    }
+
+    /// Returns the location of a key of the experience target in the config file.
+    fn target_location_of(&self, keys: &[&str]) -> String {
+        let path = ["target", "experience"]
+            .iter()
+            .chain(keys)
+            .copied()
+            .collect::<Vec<_>>();
+        self.config_file.location_of(&path)
+    }
+
+    fn check_duplicate(
+        &mut self,
+        keys: &[&str],
+        is_defined: bool,
+        included_file: &ConfigFile,
+    ) -> Result<(), String> {
+        let path = keys.join(".");
+        let location = included_file.location_of(keys);
+        if is_defined {
+            let origin = self
+                .origins
+                .get(&path)
+                .cloned()
+                .unwrap_or_else(|| self.target_location_of(keys));
+            return Err(format!(
+                "Duplicate definition of {} in {} (already defined in {})",
+                path, location, origin
+            ));
+        }
+        self.origins.insert(path, location);
+        Ok(())
+    }
+
+    fn merge(
+        &mut self,
+        target: &mut serde_yaml::Mapping,
+        included: serde_yaml::Mapping,
+        included_file: &ConfigFile,
+    ) -> Result<(), String> {
+        for (key, value) in included {
+            let property = key
+                .as_str()
+                .ok_or(format!(
+                    "Invalid property in included config file {}",
+                    included_file.path.display()
+                ))?
+                .to_owned();
+
+            if value.is_null() {
+                continue;
+            }
+
+            if INCLUDE_MAP_PROPERTIES.contains(&property.as_str()) {
+                let value = match value {
+                    serde_yaml::Value::Mapping(v) => v,
+                    _ => {
+                        return Err(format!(
+                            "Expected {} to be a map in {}",
+                            property,
+                            included_file.location_of(&[&property])
+                        ))
+                    }
+                };
+                let target = target
+                    .entry(key)
+                    .or_insert_with(|| serde_yaml::Value::Mapping(serde_yaml::Mapping::new()));
+                if target.is_null() {
+                    *target = serde_yaml::Value::Mapping(serde_yaml::Mapping::new());
+                }
+                let target = target.as_mapping_mut().ok_or(format!(
+                    "Expected {} to be a map in {}",
+                    property,
+                    self.target_location_of(&[&property])
+                ))?;
+                for (label, value) in value {
+                    let label_str = label.as_str().unwrap_or_default().to_owned();
+                    self.check_duplicate(
+                        &[&property, &label_str],
+                        target.contains_key(&label),
+                        included_file,
+                    )?;
+                    target.insert(label, value);
+                }
+            } else if INCLUDE_LIST_PROPERTIES.contains(&property.as_str()) {
+                let value = match value {
+                    serde_yaml::Value::Sequence(v) => v,
+                    _ => {
+                        return Err(format!(
+                            "Expected {} to be a list in {}",
+                            property,
+                            included_file.location_of(&[&property])
+                        ))
+                    }
+                };
+                let target = target
+                    .entry(key)
+                    .or_insert_with(|| serde_yaml::Value::Sequence(Vec::new()));
+                if target.is_null() {
+                    *target = serde_yaml::Value::Sequence(Vec::new());
+                }
+                match target {
+                    serde_yaml::Value::Sequence(target) => target.extend(value),
+                    _ => {
+                        return Err(format!(
+                            "Expected {} to be a list in {}",
+                            property,
+                            self.target_location_of(&[&property])
+                        ))
+                    }
+                }
+            } else if INCLUDE_VALUE_PROPERTIES.contains(&property.as_str()) {
+                let is_defined = target.get(&key).is_some_and(|v| !v.is_null());
+                self.check_duplicate(&[&property], is_defined, included_file)?;
+                target.insert(key, value);
+            } else {
+                return Err(format!(
+                    "Unknown property {} in {}",
+                    property,
+                    included_file.location_of(&[&property])
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn get_included_files(
+    config_file: &ConfigFile,
+    include: &[String],
+) -> Result<Vec<PathBuf>, String> {
+    let config_dir = config_file.path.parent().unwrap_or_else(|| Path::new("."));
+
+    let mut files: Vec<PathBuf> = Vec::new();
+    for (index, pattern) in include.iter().enumerate() {
+        let relative_to_config = config_dir.join(pattern);
+        let relative_to_config = relative_to_config
+            .to_str()
+            .ok_or(format!("Path was invalid: {}", pattern))?;
+        let paths = glob::glob(relative_to_config)
+            .map_err(|e| format!("Include glob pattern invalid: {}\n\t{}", pattern, e))?;
+
+        let mut matched: Vec<PathBuf> = Vec::new();
+        for path in paths {
+            let path = path.map_err(|e| format!("Include glob pattern invalid: {}", e))?;
+            if path.is_file() {
+                matched.push(path);
+            }
+        }
+        if matched.is_empty() {
+            return Err(format!(
+                "Include pattern {} in {} did not match any files",
+                pattern,
+                config_file.location_of(&["include", &index.to_string()])
+            ));
+        }
+
+        // Sort the matches of each pattern so that the merged config is stable
+        matched.sort();
+        for path in matched {
+            if !files.contains(&path) {
+                files.push(path);
+            }
+        }
+    }
+
+    Ok(files)
+}
+
 fn load_config_file(config_file: &Path) -> Result<Config, String> {
-    let data = fs::read_to_string(config_file).map_err(|e| {
-        format!(
-            "Unable to read config file: {}\n\t{}",
-            config_file.display(),
-            e
-        )
-    })?;
+    let config_file = ConfigFile::read(config_file)?;
+    let mut config = config_file.parse::<Config>()?;
 
-    serde_yaml::from_str::<Config>(&data).map_err(|e| {
-        format!(
-            "Unable to parse config file {}\n\t{}",
-            config_file.display(),
-            e
-        )
-    })
+    if config.include.is_empty() {
+        return Ok(config);
+    }
+    if !config.targets.is_empty() {
+        return Err(format!(
+            "Unable to include config files: include cannot be used with targets in {}",
+            config_file.location_of(&["include"])
+        ));
+    }
+
+    let target = config
+        .target
+        .get_mut("experience")
+        .and_then(|target| {
+            if target.is_null() {
+                *target = serde_yaml::Value::Mapping(serde_yaml::Mapping::new());
+            }
+            target.as_mapping_mut()
+        })
+        .ok_or(format!(
+            "Unable to include config files: no experience target is defined in {}",
+            config_file.path.display()
+        ))?;
+
+    let mut merger = IncludeMerger::new(&config_file);
+    for included_path in get_included_files(&config_file, &config.include)? {
+        let included_file = ConfigFile::read(&included_path)?;
+        let included = included_file.parse::<serde_yaml::Mapping>()?;
+        merger.merge(target, included, &included_file)?;
+
+        logger::log(format!(
+            "Included config file {}",
+            Paint::cyan(included_path.display())
+        ));
+    }
+
+    Ok(config)
 }
 
 pub fn load_project_config(project: Option<&str>) -> Result<(PathBuf, Config), String> {
@@ -1232,3 +2109,243 @@ pub fn load_project_config(project: Option<&str>) -> Result<(PathBuf, Config), S
 
     Ok((project_path, config))
 }
+
+/// Returns the paths of a project's config file and every file it includes.
+pub fn get_project_config_files(project: Option<&str>) -> Result<Vec<PathBuf>, String> {
+    let (_, config_path) = parse_project_path(project)?;
+    let config_file = ConfigFile::read(&config_path)?;
+    let config = config_file.parse::<Config>()?;
+
+    let mut files = get_included_files(&config_file, &config.include)?;
+    files.insert(0, config_path);
+    Ok(files)
+}
+
+/// A file listing the Mantle projects of a repository so that commands can be run for all of them.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct WorkspaceConfig {
+    /// Paths or glob patterns of the projects, relative to the workspace file.
+    projects: Vec<String>,
+}
+
+/// Returns the paths of the projects listed in a workspace file. Glob patterns only match
+/// directories containing a `mantle.yml` file and config files.
+pub fn load_workspace_projects(workspace_file: &str) -> Result<Vec<String>, String> {
+    let workspace_file = ConfigFile::read(Path::new(workspace_file))?;
+    let workspace = workspace_file.parse::<WorkspaceConfig>()?;
+    let workspace_dir = workspace_file
+        .path
+        .parent()
+        .unwrap_or_else(|| Path::new("."));
+
+    let mut projects: Vec<String> = Vec::new();
+    for (index, pattern) in workspace.projects.iter().enumerate() {
+        let relative_to_workspace = workspace_dir.join(pattern);
+        let relative_to_workspace = relative_to_workspace
+            .to_str()
+            .ok_or(format!("Path was invalid: {}", pattern))?;
+        let paths = glob::glob(relative_to_workspace)
+            .map_err(|e| format!("Project glob pattern invalid: {}\n\t{}", pattern, e))?;
+
+        let mut matched: Vec<PathBuf> = Vec::new();
+        for path in paths {
+            let path = path.map_err(|e| format!("Project glob pattern invalid: {}", e))?;
+            if path.is_file() || path.join("mantle.yml").is_file() {
+                matched.push(path);
+            }
+        }
+        if matched.is_empty() {
+            return Err(format!(
+                "Project pattern {} in {} did not match any projects",
+                pattern,
+                workspace_file.location_of(&["projects", &index.to_string()])
+            ));
+        }
+
+        matched.sort();
+        for path in matched {
+            let path = path.display().to_string();
+            if !projects.contains(&path) {
+                projects.push(path);
+            }
+        }
+    }
+
+    logger::log(format!(
+        "Loaded workspace file {} with {} project(s)",
+        Paint::cyan(workspace_file.path.display()),
+        projects.len()
+    ));
+
+    Ok(projects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "target:
+  experience:
+    places:
+      start:
+        file: start.rbxlx
+    thumbnails:
+      - thumbnails/a.png
+    icon: icon.png
+";
+
+    fn get_file(path: &str, data: &str) -> ConfigFile {
+        ConfigFile::new(Path::new(path), data.to_owned())
+    }
+
+    fn get_target(config_file: &ConfigFile) -> serde_yaml::Mapping {
+        let config = config_file.parse::<serde_yaml::Value>().unwrap();
+        config["target"]["experience"].as_mapping().unwrap().clone()
+    }
+
+    fn merge(
+        merger: &mut IncludeMerger,
+        target: &mut serde_yaml::Mapping,
+        included_file: &ConfigFile,
+    ) -> Result<(), String> {
+        let included = included_file.parse::<serde_yaml::Mapping>().unwrap();
+        merger.merge(target, included, included_file)
+    }
+
+    #[test]
+    fn location_of_uses_yaml_positions() {
+        let config_file = get_file(
+            "mantle.yml",
+            "include:
+  - products.yml
+  - 'badges.yml'
+target:
+  experience:
+    products:
+      include: {}
+",
+        );
+
+        assert_eq!(config_file.location_of(&["include"]), "mantle.yml:1");
+        assert_eq!(config_file.location_of(&["include", "1"]), "mantle.yml:3");
+        assert_eq!(
+            config_file.location_of(&["target", "experience", "products", "include"]),
+            "mantle.yml:7"
+        );
+        assert_eq!(config_file.location_of(&["environments"]), "mantle.yml");
+    }
+
+    #[test]
+    fn merges_included_files() {
+        let config_file = get_file("mantle.yml", CONFIG);
+        let mut target = get_target(&config_file);
+        let mut merger = IncludeMerger::new(&config_file);
+        merge(
+            &mut merger,
+            &mut target,
+            &get_file(
+                "places.yml",
+                "places:
+  lobby:
+    file: lobby.rbxlx
+thumbnails:
+  - thumbnails/b.png
+spatialVoice:
+  enabled: true
+",
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(
+            serde_yaml::Value::Mapping(target),
+            serde_yaml::from_str::<serde_yaml::Value>(
+                "places:
+  start:
+    file: start.rbxlx
+  lobby:
+    file: lobby.rbxlx
+thumbnails:
+  - thumbnails/a.png
+  - thumbnails/b.png
+icon: icon.png
+spatialVoice:
+  enabled: true
+"
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_labels_defined_in_the_config_file() {
+        let config_file = get_file("mantle.yml", CONFIG);
+        let mut target = get_target(&config_file);
+        let mut merger = IncludeMerger::new(&config_file);
+        let result = merge(
+            &mut merger,
+            &mut target,
+            &get_file(
+                "places.yml",
+                "places:
+  lobby:
+    file: lobby.rbxlx
+  start:
+    file: other.rbxlx
+",
+            ),
+        );
+
+        assert_eq!(
+            result,
+            Err(
+                "Duplicate definition of places.start in places.yml:4 (already defined in mantle.yml:4)"
+                    .to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_values_defined_in_other_included_files() {
+        let config_file = get_file("mantle.yml", CONFIG);
+        let mut target = get_target(&config_file);
+        let mut merger = IncludeMerger::new(&config_file);
+        merge(
+            &mut merger,
+            &mut target,
+            &get_file("voice.yml", "spatialVoice:\n  enabled: true\n"),
+        )
+        .unwrap();
+        let result = merge(
+            &mut merger,
+            &mut target,
+            &get_file("other.yml", "\nspatialVoice:\n  enabled: false\n"),
+        );
+
+        assert_eq!(
+            result,
+            Err(
+                "Duplicate definition of spatialVoice in other.yml:2 (already defined in voice.yml:1)"
+                    .to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_properties() {
+        let config_file = get_file("mantle.yml", CONFIG);
+        let mut target = get_target(&config_file);
+        let mut merger = IncludeMerger::new(&config_file);
+        let result = merge(
+            &mut merger,
+            &mut target,
+            &get_file("places.yml", "places: {}\nplayability: public\n"),
+        );
+
+        assert_eq!(
+            result,
+            Err("Unknown property playability in places.yml:2".to_owned())
+        );
+    }
+}
@@ -0,0 +1,578 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs,
+    path::Path,
+};
+
+use rbx_api::{models::AssetId, RobloxApi};
+use url::Url;
+use yansi::Paint;
+
+use super::{
+    config::{
+        BadgeTargetConfig, ExperienceTargetConfig, ExperienceTargetConfigurationConfig,
+        NotificationTargetConfig, OwnerConfig, PassTargetConfig, PlaceTargetConfig,
+        PlayabilityTargetConfig, ProductTargetConifg, SocialLinkTargetConfig,
+        SpatialVoiceTargetConfig, TargetConfig,
+    },
+    project::ProjectTarget,
+    resource_graph::{Resource, ResourceGraph},
+    roblox_resource_manager::*,
+    state::get_desired_graph,
+};
+
+/// The properties of an experience target which are maps keyed by label. Their entries are sorted
+/// when the generated config is written so that it is stable between imports.
+const LABELLED_PROPERTIES: [&str; 5] = ["places", "products", "passes", "badges", "notifications"];
+
+/// Generates a camel case label from a resource's display name (e.g. `Gold Pack` becomes
+/// `goldPack`). Labels are made unique within `used_labels`.
+fn get_label(name: &str, fallback: &str, used_labels: &mut BTreeSet<String>) -> String {
+    let words: Vec<String> = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_ascii_lowercase())
+        .collect();
+
+    let mut label = String::new();
+    for word in words.iter() {
+        if label.is_empty() {
+            label.push_str(word);
+        } else {
+            let mut chars = word.chars();
+            if let Some(first) = chars.next() {
+                label.push(first.to_ascii_uppercase());
+                label.push_str(chars.as_str());
+            }
+        }
+    }
+    if !label.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        label = format!("{}{}", fallback, label);
+    }
+
+    let mut unique_label = label.clone();
+    let mut index = 2;
+    while used_labels.contains(&unique_label) {
+        unique_label = format!("{}{}", label, index);
+        index += 1;
+    }
+    used_labels.insert(unique_label.clone());
+    unique_label
+}
+
+fn get_image_extension(data: &[u8]) -> &'static str {
+    if data.starts_with(&[0xff, 0xd8, 0xff]) {
+        "jpg"
+    } else if data.starts_with(b"GIF8") {
+        "gif"
+    } else if data.starts_with(b"BM") {
+        "bmp"
+    } else {
+        "png"
+    }
+}
+
+fn get_place_extension(data: &[u8]) -> &'static str {
+    if data.starts_with(b"<roblox!") {
+        "rbxl"
+    } else {
+        "rbxlx"
+    }
+}
+
+fn write_file(project_path: &Path, file: &str, data: &[u8]) -> Result<(), String> {
+    let file_path = project_path.join(file);
+    if file_path.exists() {
+        return Err(format!(
+            "Unable to write {}: the file already exists",
+            file_path.display()
+        ));
+    }
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Unable to create directory {}: {}", parent.display(), e))?;
+    }
+    fs::write(&file_path, data)
+        .map_err(|e| format!("Unable to write {}: {}", file_path.display(), e))
+}
+
+async fn download_image(
+    roblox_api: &RobloxApi,
+    project_path: &Path,
+    folder: &str,
+    name: &str,
+    asset_id: AssetId,
+) -> Result<String, String> {
+    let data = roblox_api.download_asset(asset_id).await?;
+    let file = format!("{}/{}.{}", folder, name, get_image_extension(&data));
+    write_file(project_path, &file, &data)?;
+    logger::log(format!("Downloaded {}", Paint::cyan(&file)));
+    Ok(file)
+}
+
+fn get_asset_id(outputs: Option<RobloxOutputs>) -> Option<AssetId> {
+    match outputs {
+        Some(RobloxOutputs::Place(outputs))
+        | Some(RobloxOutputs::ExperienceThumbnail(outputs))
+        | Some(RobloxOutputs::ProductIcon(outputs))
+        | Some(RobloxOutputs::BadgeIcon(outputs)) => Some(outputs.asset_id),
+        Some(RobloxOutputs::Pass(outputs)) => Some(outputs.icon_asset_id),
+        _ => None,
+    }
+}
+
+struct ConfigGenerator<'a> {
+    roblox_api: &'a RobloxApi,
+    project_path: &'a Path,
+    imported_resources: BTreeMap<String, RobloxResource>,
+    /// Maps the ID each resource will have once deployed from the generated config to the ID it
+    /// was imported with.
+    renames: HashMap<String, String>,
+}
+
+impl<'a> ConfigGenerator<'a> {
+    fn get(&self, resource_id: &str) -> Option<&RobloxResource> {
+        self.imported_resources.get(resource_id)
+    }
+
+    fn rename(&mut self, resource_id: String, imported_id: &str) {
+        self.renames.insert(resource_id, imported_id.to_owned());
+    }
+
+    /// Returns the IDs and inputs of all imported resources with the given type prefix.
+    fn list(&self, prefix: &str) -> Vec<(String, RobloxInputs)> {
+        self.imported_resources
+            .iter()
+            .filter(|(id, _)| id.starts_with(&format!("{}_", prefix)))
+            .map(|(id, resource)| (id.clone(), resource.get_inputs()))
+            .collect()
+    }
+
+    async fn generate_places(&mut self) -> Result<HashMap<String, PlaceTargetConfig>, String> {
+        let mut used_labels = BTreeSet::from(["start".to_owned()]);
+        let mut places = HashMap::new();
+        for (imported_id, inputs) in self.list("place") {
+            let imported_label = imported_id.trim_start_matches("place_").to_owned();
+            let is_start = matches!(inputs, RobloxInputs::Place(PlaceInputs { is_start: true }));
+
+            let configuration = match self
+                .get(&format!("placeConfiguration_{}", imported_label))
+                .map(|resource| resource.get_inputs())
+            {
+                Some(RobloxInputs::PlaceConfiguration(model)) => Some(model),
+                _ => None,
+            };
+            let label = if is_start {
+                "start".to_owned()
+            } else {
+                let name = configuration
+                    .as_ref()
+                    .map(|model| model.name.clone())
+                    .unwrap_or_default();
+                get_label(&name, "place", &mut used_labels)
+            };
+
+            let asset_id = get_asset_id(self.get(&imported_id).and_then(|r| r.get_outputs()))
+                .ok_or(format!("Imported place {} has no asset ID", imported_label))?;
+            let data = self.roblox_api.download_asset(asset_id).await?;
+            let file = format!("places/{}.{}", label, get_place_extension(&data));
+            write_file(self.project_path, &file, &data)?;
+            logger::log(format!("Downloaded {}", Paint::cyan(&file)));
+
+            self.rename(format!("place_{}", label), &imported_id);
+            self.rename(
+                format!("placeFile_{}", label),
+                &format!("placeFile_{}", imported_label),
+            );
+            self.rename(
+                format!("placeConfiguration_{}", label),
+                &format!("placeConfiguration_{}", imported_label),
+            );
+
+            places.insert(
+                label,
+                PlaceTargetConfig {
+                    file: Some(file),
+                    configuration: configuration.as_ref().map(Into::into),
+                },
+            );
+        }
+        Ok(places)
+    }
+
+    async fn generate_thumbnails(&mut self) -> Result<Vec<String>, String> {
+        // The thumbnail order's dependencies list the thumbnails in the order they are displayed
+        let imported_ids = self
+            .get("experienceThumbnailOrder_singleton")
+            .map(|resource| resource.get_dependencies())
+            .unwrap_or_default();
+
+        let mut thumbnails = Vec::new();
+        for imported_id in imported_ids
+            .iter()
+            .filter(|id| id.starts_with("experienceThumbnail_"))
+        {
+            let asset_id =
+                get_asset_id(self.get(imported_id).and_then(|r| r.get_outputs())).ok_or(
+                    format!("Imported thumbnail {} has no asset ID", imported_id),
+                )?;
+            let file = download_image(
+                self.roblox_api,
+                self.project_path,
+                "thumbnails",
+                &(thumbnails.len() + 1).to_string(),
+                asset_id,
+            )
+            .await?;
+            self.rename(format!("experienceThumbnail_{}", file), imported_id);
+            thumbnails.push(file);
+        }
+        Ok(thumbnails)
+    }
+
+    async fn generate_products(&mut self) -> Result<HashMap<String, ProductTargetConifg>, String> {
+        let mut used_labels = BTreeSet::new();
+        let mut products = HashMap::new();
+        for (imported_id, inputs) in self.list("product") {
+            let inputs = match inputs {
+                RobloxInputs::Product(inputs) => inputs,
+                _ => continue,
+            };
+            let imported_label = imported_id.trim_start_matches("product_").to_owned();
+            let label = get_label(&inputs.name, "product", &mut used_labels);
+            self.rename(format!("product_{}", label), &imported_id);
+
+            let imported_icon_id = format!("productIcon_{}", imported_label);
+            let icon_asset_id = get_asset_id(
+                self.get(&imported_icon_id)
+                    .and_then(|resource| resource.get_outputs()),
+            );
+            let icon = match icon_asset_id {
+                Some(asset_id) => {
+                    self.rename(format!("productIcon_{}", label), &imported_icon_id);
+                    Some(
+                        download_image(
+                            self.roblox_api,
+                            self.project_path,
+                            "products",
+                            &label,
+                            asset_id,
+                        )
+                        .await?,
+                    )
+                }
+                None => None,
+            };
+
+            products.insert(
+                label,
+                ProductTargetConifg {
+                    name: inputs.name,
+                    description: Some(inputs.description).filter(|d| !d.is_empty()),
+                    icon,
+                    price: inputs.price,
+                },
+            );
+        }
+        Ok(products)
+    }
+
+    async fn generate_passes(&mut self) -> Result<HashMap<String, PassTargetConfig>, String> {
+        let mut used_labels = BTreeSet::new();
+        let mut passes = HashMap::new();
+        for (imported_id, inputs) in self.list("pass") {
+            let inputs = match inputs {
+                RobloxInputs::Pass(inputs) => inputs,
+                _ => continue,
+            };
+            let label = get_label(&inputs.name, "pass", &mut used_labels);
+            self.rename(format!("pass_{}", label), &imported_id);
+
+            let asset_id = get_asset_id(self.get(&imported_id).and_then(|r| r.get_outputs()))
+                .ok_or(format!("Imported pass {} has no icon", inputs.name))?;
+            let icon = download_image(
+                self.roblox_api,
+                self.project_path,
+                "passes",
+                &label,
+                asset_id,
+            )
+            .await?;
+
+            passes.insert(
+                label,
+                PassTargetConfig {
+                    name: inputs.name,
+                    description: Some(inputs.description).filter(|d| !d.is_empty()),
+                    icon,
+                    price: inputs.price,
+                },
+            );
+        }
+        Ok(passes)
+    }
+
+    async fn generate_badges(&mut self) -> Result<HashMap<String, BadgeTargetConfig>, String> {
+        let mut used_labels = BTreeSet::new();
+        let mut badges = HashMap::new();
+        for (imported_id, inputs) in self.list("badge") {
+            let inputs = match inputs {
+                RobloxInputs::Badge(inputs) => inputs,
+                _ => continue,
+            };
+            let imported_label = imported_id.trim_start_matches("badge_").to_owned();
+            let label = get_label(&inputs.name, "badge", &mut used_labels);
+            self.rename(format!("badge_{}", label), &imported_id);
+
+            let imported_icon_id = format!("badgeIcon_{}", imported_label);
+            let asset_id = get_asset_id(
+                self.get(&imported_icon_id)
+                    .and_then(|resource| resource.get_outputs()),
+            )
+            .ok_or(format!("Imported badge {} has no icon", inputs.name))?;
+            self.rename(format!("badgeIcon_{}", label), &imported_icon_id);
+            let icon = download_image(
+                self.roblox_api,
+                self.project_path,
+                "badges",
+                &label,
+                asset_id,
+            )
+            .await?;
+
+            badges.insert(
+                label,
+                BadgeTargetConfig {
+                    name: inputs.name,
+                    description: Some(inputs.description).filter(|d| !d.is_empty()),
+                    icon,
+                    enabled: if inputs.enabled { None } else { Some(false) },
+                },
+            );
+        }
+        Ok(badges)
+    }
+
+    fn generate_notifications(&mut self) -> HashMap<String, NotificationTargetConfig> {
+        let mut used_labels = BTreeSet::new();
+        let mut notifications = HashMap::new();
+        for (imported_id, inputs) in self.list("notification") {
+            let inputs = match inputs {
+                RobloxInputs::Notification(inputs) => inputs,
+                _ => continue,
+            };
+            let label = get_label(&inputs.name, "notification", &mut used_labels);
+            self.rename(format!("notification_{}", label), &imported_id);
+
+            notifications.insert(
+                label.clone(),
+                NotificationTargetConfig {
+                    name: Some(inputs.name).filter(|name| *name != label),
+                    content: inputs.content,
+                },
+            );
+        }
+        notifications
+    }
+
+    fn generate_social_links(&self) -> Result<Vec<SocialLinkTargetConfig>, String> {
+        let mut social_links = Vec::new();
+        for (_, inputs) in self.list("socialLink") {
+            if let RobloxInputs::SocialLink(inputs) = inputs {
+                social_links.push(SocialLinkTargetConfig {
+                    title: inputs.title,
+                    url: Url::parse(&inputs.url)
+                        .map_err(|e| format!("Invalid social link URL {}: {}", inputs.url, e))?,
+                });
+            }
+        }
+        Ok(social_links)
+    }
+
+    fn generate_configuration(&self) -> Option<ExperienceTargetConfigurationConfig> {
+        let model = match self
+            .get("experienceConfiguration_singleton")
+            .map(|resource| resource.get_inputs())
+        {
+            Some(RobloxInputs::ExperienceConfiguration(model)) => model,
+            _ => return None,
+        };
+
+        let mut configuration = ExperienceTargetConfigurationConfig::from(&model);
+        // Inactive experiences are private regardless of their friends-only setting
+        if let Some(RobloxInputs::ExperienceActivation(ExperienceActivationInputs {
+            is_active: false,
+        })) = self
+            .get("experienceActivation_singleton")
+            .map(|resource| resource.get_inputs())
+        {
+            configuration.playability = Some(PlayabilityTargetConfig::Private);
+        }
+        Some(configuration)
+    }
+
+    async fn generate(&mut self) -> Result<ExperienceTargetConfig, String> {
+        let places = self.generate_places().await?;
+        let thumbnails = self.generate_thumbnails().await?;
+        let products = self.generate_products().await?;
+        let passes = self.generate_passes().await?;
+        let badges = self.generate_badges().await?;
+        let notifications = self.generate_notifications();
+        let social_links = self.generate_social_links()?;
+
+        let spatial_voice = match self
+            .get("spatialVoice_singleton")
+            .map(|resource| resource.get_inputs())
+        {
+            Some(RobloxInputs::SpatialVoice(inputs)) => Some(SpatialVoiceTargetConfig {
+                enabled: inputs.enabled,
+            }),
+            _ => None,
+        };
+
+        if !self.list("asset").is_empty() {
+            logger::log(Paint::yellow(
+                "Assets were not imported: add them to the generated config manually",
+            ));
+        }
+
+        Ok(ExperienceTargetConfig {
+            configuration: self.generate_configuration(),
+            places: Some(places),
+            icon: None,
+            thumbnails: Some(thumbnails).filter(|t| !t.is_empty()),
+            social_links: Some(social_links).filter(|s| !s.is_empty()),
+            products: Some(products).filter(|p| !p.is_empty()),
+            passes: Some(passes).filter(|p| !p.is_empty()),
+            badges: Some(badges).filter(|b| !b.is_empty()),
+            assets: None,
+            spatial_voice,
+            notifications: Some(notifications).filter(|n| !n.is_empty()),
+        })
+    }
+
+    /// Builds the state which would result from deploying `experience_config` to the imported
+    /// experience, so that the first deployment after the import has nothing to do.
+    fn normalize_graph(
+        &self,
+        experience_config: &ExperienceTargetConfig,
+        owner_config: &OwnerConfig,
+    ) -> Result<ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>, String> {
+        let desired_graph = get_desired_graph(
+            self.project_path,
+            &[ProjectTarget {
+                label: None,
+                config: TargetConfig::Experience(experience_config.clone()),
+            }],
+            owner_config,
+        )?;
+
+        let mut resources: BTreeMap<String, RobloxResource> = BTreeMap::new();
+        for mut resource in desired_graph.get_resource_list() {
+            let resource_id = resource.get_id();
+            let imported_id = self.renames.get(&resource_id).unwrap_or(&resource_id);
+            if let Some(outputs) = self.get(imported_id).and_then(|r| r.get_outputs()) {
+                resource.set_outputs(outputs);
+                resources.insert(resource_id, resource);
+            }
+        }
+
+        // Resources are only known to exist if all of their dependencies exist
+        loop {
+            let missing: Vec<String> = resources
+                .iter()
+                .filter(|(_, resource)| {
+                    resource
+                        .get_dependencies()
+                        .iter()
+                        .any(|dependency| !resources.contains_key(dependency))
+                })
+                .map(|(id, _)| id.clone())
+                .collect();
+            if missing.is_empty() {
+                break;
+            }
+            for resource_id in missing {
+                resources.remove(&resource_id);
+            }
+        }
+
+        Ok(ResourceGraph::new(
+            &resources.into_values().collect::<Vec<_>>(),
+        ))
+    }
+}
+
+/// Sorts the labelled maps of the generated config and removes unset properties so that the
+/// written file only contains what was imported.
+fn clean_yaml(value: serde_yaml::Value, sort: bool) -> serde_yaml::Value {
+    match value {
+        serde_yaml::Value::Mapping(mapping) => {
+            let mut entries: Vec<(serde_yaml::Value, serde_yaml::Value)> = mapping
+                .into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| {
+                    let sort_children = matches!(
+                        key.as_str(),
+                        Some(key) if LABELLED_PROPERTIES.contains(&key)
+                    );
+                    (key, clean_yaml(value, sort_children))
+                })
+                .collect();
+            if sort {
+                entries.sort_by(|(a, _), (b, _)| a.as_str().cmp(&b.as_str()));
+            }
+            serde_yaml::Value::Mapping(entries.into_iter().collect())
+        }
+        serde_yaml::Value::Sequence(sequence) => serde_yaml::Value::Sequence(
+            sequence
+                .into_iter()
+                .map(|value| clean_yaml(value, false))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+/// Generates an experience target config from an imported graph, downloading the files it
+/// references into the project, and writes it to `config_file` (relative to the project). The
+/// generated file has the same shape as the `target.experience` property so that it can be added
+/// to the project's `include` list.
+///
+/// Returns the imported graph normalized to the generated config.
+pub async fn import_config(
+    roblox_api: &RobloxApi,
+    project_path: &Path,
+    config_file: &str,
+    imported_graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+    owner_config: &OwnerConfig,
+) -> Result<ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>, String> {
+    let config_path = project_path.join(config_file);
+    if config_path.exists() {
+        return Err(format!(
+            "Unable to write config to {}: the file already exists",
+            config_path.display()
+        ));
+    }
+
+    let mut generator = ConfigGenerator {
+        roblox_api,
+        project_path,
+        imported_resources: imported_graph
+            .get_resource_list()
+            .into_iter()
+            .map(|resource| (resource.get_id(), resource))
+            .collect(),
+        renames: HashMap::new(),
+    };
+    let experience_config = generator.generate().await?;
+
+    let value = serde_yaml::to_value(&experience_config)
+        .map_err(|e| format!("Unable to serialize config: {}", e))?;
+    let data = serde_yaml::to_string(&clean_yaml(value, false))
+        .map_err(|e| format!("Unable to serialize config: {}", e))?;
+    write_file(project_path, config_file, data.as_bytes())?;
+    logger::log(format!("Wrote config file {}", Paint::cyan(config_file)));
+
+    generator.normalize_graph(&experience_config, owner_config)
+}
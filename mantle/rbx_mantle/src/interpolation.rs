@@ -0,0 +1,239 @@
+use std::{collections::BTreeMap, env, path::PathBuf};
+
+use serde_yaml::{Mapping, Value};
+
+use super::project::{get_current_branch, get_current_commit};
+
+const EXPRESSION_START: &str = "${{";
+const EXPRESSION_END: &str = "}}";
+
+pub struct InterpolationContext {
+    project_path: PathBuf,
+    environment_label: String,
+    variables: BTreeMap<String, String>,
+    git_values: BTreeMap<String, String>,
+}
+
+impl InterpolationContext {
+    pub fn new(
+        project_path: PathBuf,
+        environment_label: &str,
+        variables: BTreeMap<String, String>,
+    ) -> Result<Self, String> {
+        let mut context = Self {
+            project_path,
+            environment_label: environment_label.to_owned(),
+            variables: BTreeMap::new(),
+            git_values: BTreeMap::new(),
+        };
+
+        // Variables may reference any other namespace, but not other variables
+        let mut resolved_variables = BTreeMap::new();
+        for (name, value) in variables {
+            let value = context.interpolate_string(&value, &format!("variables.{}", name))?;
+            resolved_variables.insert(name, value);
+        }
+        context.variables = resolved_variables;
+
+        Ok(context)
+    }
+
+    fn resolve_git(&mut self, name: &str) -> Result<String, String> {
+        if let Some(value) = self.git_values.get(name) {
+            return Ok(value.clone());
+        }
+
+        let value = match name {
+            "sha" => get_current_commit(self.project_path.clone())?,
+            "branch" => get_current_branch(self.project_path.clone())?,
+            _ => return Err(format!("Unknown git property {}", name)),
+        };
+        self.git_values.insert(name.to_owned(), value.clone());
+
+        Ok(value)
+    }
+
+    fn resolve(&mut self, expression: &str) -> Result<String, String> {
+        let (namespace, name) = expression.split_once('.').ok_or(format!(
+            "Invalid expression {}. Expected the format <namespace>.<name>",
+            expression
+        ))?;
+
+        match namespace {
+            "env" => {
+                env::var(name).map_err(|_| format!("Environment variable {} is not set", name))
+            }
+            "environment" => match name {
+                "label" => Ok(self.environment_label.clone()),
+                _ => Err(format!("Unknown environment property {}", name)),
+            },
+            "git" => self.resolve_git(name),
+            "variables" => self
+                .variables
+                .get(name)
+                .cloned()
+                .ok_or(format!("Variable {} is not defined", name)),
+            _ => Err(format!("Unknown namespace {}", namespace)),
+        }
+    }
+
+    fn interpolate_string(&mut self, value: &str, path: &str) -> Result<String, String> {
+        let mut result = String::new();
+        let mut rest = value;
+        while let Some(start) = rest.find(EXPRESSION_START) {
+            result.push_str(&rest[..start]);
+
+            let remaining = &rest[start + EXPRESSION_START.len()..];
+            let end = remaining
+                .find(EXPRESSION_END)
+                .ok_or(format!("Unterminated expression in {}: {}", path, value))?;
+            let expression = remaining[..end].trim();
+            let resolved = self.resolve(expression).map_err(|e| {
+                format!(
+                    "Unable to resolve ${{{{ {} }}}} in {}\n\t{}",
+                    expression, path, e
+                )
+            })?;
+            result.push_str(&resolved);
+
+            rest = &remaining[end + EXPRESSION_END.len()..];
+        }
+        result.push_str(rest);
+
+        Ok(result)
+    }
+
+    /// Resolves all expressions in the string values of `value`. `path` is the path to `value` in
+    /// the config file and is used for error messages.
+    pub fn interpolate(&mut self, value: Value, path: &str) -> Result<Value, String> {
+        Ok(match value {
+            Value::String(value) if value.contains(EXPRESSION_START) => {
+                let trimmed = value.trim();
+                let is_single_expression = trimmed.starts_with(EXPRESSION_START)
+                    && trimmed.ends_with(EXPRESSION_END)
+                    && trimmed.matches(EXPRESSION_START).count() == 1;
+
+                let resolved = self.interpolate_string(&value, path)?;
+
+                // Allow a value which is only an expression to resolve to a non-string scalar
+                // (e.g. `price: ${{ variables.price }}`)
+                match serde_yaml::from_str::<Value>(&resolved) {
+                    Ok(scalar @ (Value::Number(_) | Value::Bool(_))) if is_single_expression => {
+                        scalar
+                    }
+                    _ => Value::String(resolved),
+                }
+            }
+            Value::Sequence(sequence) => {
+                let mut result = Vec::new();
+                for (index, value) in sequence.into_iter().enumerate() {
+                    result.push(self.interpolate(value, &format!("{}[{}]", path, index))?);
+                }
+                Value::Sequence(result)
+            }
+            Value::Mapping(mapping) => {
+                let mut result = Mapping::new();
+                for (key, value) in mapping {
+                    let value_path = match key.as_str() {
+                        Some(key) => format!("{}.{}", path, key),
+                        None => path.to_owned(),
+                    };
+                    let value = self.interpolate(value, &value_path)?;
+                    result.insert(key, value);
+                }
+                Value::Mapping(result)
+            }
+            value => value,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_context(variables: &[(&str, &str)]) -> InterpolationContext {
+        InterpolationContext::new(
+            PathBuf::from("."),
+            "production",
+            variables
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    fn interpolate(context: &mut InterpolationContext, value: &str) -> Result<Value, String> {
+        context.interpolate(Value::String(value.to_owned()), "target.experience")
+    }
+
+    #[test]
+    fn coerces_single_expressions_to_numbers_and_bools() {
+        let mut context = get_context(&[("price", "100"), ("enabled", "true"), ("name", "Gems")]);
+
+        let price = interpolate(&mut context, "${{ variables.price }}").unwrap();
+        assert!(price.is_number());
+        assert_eq!(price.as_u64(), Some(100));
+        assert_eq!(
+            interpolate(&mut context, " ${{variables.enabled}} ").unwrap(),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            interpolate(&mut context, "${{ variables.name }}").unwrap(),
+            Value::String("Gems".to_owned())
+        );
+    }
+
+    #[test]
+    fn keeps_strings_with_multiple_expressions() {
+        let mut context = get_context(&[("price", "100")]);
+
+        assert_eq!(
+            interpolate(&mut context, "${{ variables.price }} Robux").unwrap(),
+            Value::String("100 Robux".to_owned())
+        );
+        assert_eq!(
+            interpolate(&mut context, "${{ variables.price }}${{ variables.price }}").unwrap(),
+            Value::String("100100".to_owned())
+        );
+        assert_eq!(
+            interpolate(
+                &mut context,
+                "${{ environment.label }}-${{ variables.price }}"
+            )
+            .unwrap(),
+            Value::String("production-100".to_owned())
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_variables() {
+        let mut context = get_context(&[]);
+
+        assert_eq!(
+            interpolate(&mut context, "${{ variables.price }}"),
+            Err(
+                "Unable to resolve ${{ variables.price }} in target.experience\n\tVariable price is not defined"
+                    .to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_variables_which_reference_variables() {
+        let result = InterpolationContext::new(
+            PathBuf::from("."),
+            "production",
+            BTreeMap::from([("a".to_owned(), "${{ variables.b }}".to_owned())]),
+        );
+
+        assert_eq!(
+            result.err(),
+            Some(
+                "Unable to resolve ${{ variables.b }} in variables.a\n\tVariable b is not defined"
+                    .to_owned()
+            )
+        );
+    }
+}
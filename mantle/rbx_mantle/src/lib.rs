@@ -1,5 +1,9 @@
 pub mod config;
+pub mod import_config;
+pub mod interpolation;
 pub mod project;
+pub mod reconciliation;
 pub mod resource_graph;
 pub mod roblox_resource_manager;
 pub mod state;
+pub mod validation;
@@ -1,4 +1,4 @@
-use std::{path::PathBuf, process::Command, str};
+use std::{collections::BTreeMap, env, path::PathBuf, process::Command, str};
 
 use rbx_api::{models::CreatorType, places::models::DEFAULT_PLACE_NAME};
 use yansi::Paint;
@@ -9,6 +9,7 @@ use super::{
         OwnerConfig, PaymentsConfig, PlaceTargetConfigurationConfig, PlayabilityTargetConfig,
         StateConfig, TargetAccessConfig, TargetConfig, TargetNamePrefixConfig,
     },
+    interpolation::InterpolationContext,
     resource_graph::ResourceGraph,
     roblox_resource_manager::{RobloxInputs, RobloxOutputs, RobloxResource},
     state::{get_previous_state, ResourceStateVLatest},
@@ -30,7 +31,70 @@ fn run_command(dir: PathBuf, command: &str) -> std::io::Result<std::process::Out
     }
 }
 
-fn get_current_branch(project_path: PathBuf) -> Result<String, String> {
+fn get_env_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+/// Gets the branch from the variables set by common CI providers, which usually check out a
+/// detached HEAD.
+fn get_ci_branch() -> Option<String> {
+    // Set by GitHub Actions for pull requests
+    if let Some(branch) = get_env_var("GITHUB_HEAD_REF") {
+        return Some(branch);
+    }
+    if get_env_var("GITHUB_REF_TYPE").as_deref() != Some("tag") {
+        if let Some(branch) = get_env_var("GITHUB_REF_NAME") {
+            return Some(branch);
+        }
+    }
+    if get_env_var("CI_COMMIT_TAG").is_none() {
+        if let Some(branch) = get_env_var("CI_COMMIT_REF_NAME") {
+            return Some(branch);
+        }
+    }
+    if get_env_var("BUILDKITE_TAG").is_none() {
+        if let Some(branch) = get_env_var("BUILDKITE_BRANCH") {
+            return Some(branch);
+        }
+    }
+    None
+}
+
+fn get_ci_tag() -> Option<String> {
+    if get_env_var("GITHUB_REF_TYPE").as_deref() == Some("tag") {
+        if let Some(tag) = get_env_var("GITHUB_REF_NAME") {
+            return Some(tag);
+        }
+    }
+    get_env_var("CI_COMMIT_TAG").or_else(|| get_env_var("BUILDKITE_TAG"))
+}
+
+/// Gets the tags which point at the current commit.
+fn get_current_tags(project_path: PathBuf) -> Vec<String> {
+    let mut tags = Vec::new();
+    if let Some(tag) = get_ci_tag() {
+        tags.push(tag);
+    }
+
+    if let Ok(result) = run_command(project_path, "git tag --points-at HEAD") {
+        if result.status.success() {
+            for tag in str::from_utf8(&result.stdout).unwrap_or_default().lines() {
+                let tag = tag.trim();
+                if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+                    tags.push(tag.to_owned());
+                }
+            }
+        }
+    }
+
+    tags
+}
+
+pub(crate) fn get_current_branch(project_path: PathBuf) -> Result<String, String> {
+    if let Some(branch) = get_ci_branch() {
+        return Ok(branch);
+    }
+
     let output = run_command(project_path, "git symbolic-ref --short HEAD");
     let result = match output {
         Ok(v) => v,
@@ -54,42 +118,339 @@ fn get_current_branch(project_path: PathBuf) -> Result<String, String> {
     Ok(current_branch.to_owned())
 }
 
-fn match_branch(branch: &str, patterns: &[String]) -> bool {
+pub(crate) fn get_current_commit(project_path: PathBuf) -> Result<String, String> {
+    let output = run_command(project_path, "git rev-parse HEAD");
+    let result = match output {
+        Ok(v) => v,
+        Err(e) => {
+            return Err(format!(
+                "Unable to determine git commit. Are you in a git repository?\n\t{}",
+                e
+            ))
+        }
+    };
+
+    if !result.status.success() {
+        return Err("Unable to determine git commit. Are you in a git repository?".to_string());
+    }
+
+    let current_commit = str::from_utf8(&result.stdout).unwrap().trim();
+    if current_commit.is_empty() {
+        return Err("Unable to determine git commit. Are you in a git repository?".to_string());
+    }
+
+    Ok(current_commit.to_owned())
+}
+
+/// Returns the names of the branches on the git remote.
+pub fn get_remote_branches(project_path: PathBuf, remote: &str) -> Result<Vec<String>, String> {
+    let result = run_command(project_path, &format!("git ls-remote --heads {}", remote))
+        .map_err(|e| format!("Unable to list the branches of remote {}: {}", remote, e))?;
+    if !result.status.success() {
+        return Err(format!(
+            "Unable to list the branches of remote {}: {}",
+            remote,
+            str::from_utf8(&result.stderr).unwrap_or_default().trim()
+        ));
+    }
+
+    Ok(str::from_utf8(&result.stdout)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| line.split_once("refs/heads/"))
+        .map(|(_, branch)| branch.trim().to_owned())
+        .collect())
+}
+
+/// Returns whether the value matches any of the glob patterns.
+pub fn match_patterns(value: &str, patterns: &[String]) -> bool {
     for pattern in patterns {
         let glob_pattern = glob::Pattern::new(pattern);
-        if glob_pattern.is_ok() && glob_pattern.unwrap().matches(branch) {
+        if glob_pattern.is_ok() && glob_pattern.unwrap().matches(value) {
             return true;
         }
     }
     false
 }
 
-fn override_yaml(a: &mut serde_yaml::Value, b: serde_yaml::Value) {
+const BRANCH_PLACEHOLDER: &str = "{branch}";
+
+/// Converts a branch name such as `feature/New-Shop` into `feature-new-shop` so that it can be
+/// used in an environment label.
+pub fn get_branch_slug(branch: &str) -> String {
+    let mut slug = String::new();
+    for c in branch.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_owned()
+}
+
+/// Returns whether the environment's label is a template such as `pr-{branch}`, which creates an
+/// environment for each branch.
+pub fn is_ephemeral_environment(environment: &EnvironmentConfig) -> bool {
+    environment.label.contains(BRANCH_PLACEHOLDER)
+}
+
+/// Returns the label of the ephemeral environment for the branch, e.g. `pr-feature-new-shop` for
+/// the label template `pr-{branch}` and the branch `feature/new-shop`.
+pub fn get_branch_environment_label(label_template: &str, branch: &str) -> String {
+    label_template.replace(BRANCH_PLACEHOLDER, &get_branch_slug(branch))
+}
+
+fn with_label(environment: &EnvironmentConfig, label: String) -> EnvironmentConfig {
+    EnvironmentConfig {
+        label,
+        label_template: Some(environment.label.clone()),
+        ..environment.clone()
+    }
+}
+
+/// Finds the environment with the label. Labels which do not match an environment exactly are
+/// matched against the templated labels of ephemeral environments, e.g. `pr-shop` matches
+/// `pr-{branch}`.
+pub fn find_environment(
+    environments: &[EnvironmentConfig],
+    label: &str,
+) -> Option<EnvironmentConfig> {
+    if let Some(environment) = environments.iter().find(|e| e.label == label) {
+        return Some(environment.clone());
+    }
+
+    environments
+        .iter()
+        .filter(|e| is_ephemeral_environment(e))
+        .find(|e| {
+            let (prefix, suffix) = e.label.split_once(BRANCH_PLACEHOLDER).unwrap();
+            label.len() > prefix.len() + suffix.len()
+                && label.starts_with(prefix)
+                && label.ends_with(suffix)
+                && {
+                    let slug = &label[prefix.len()..label.len() - suffix.len()];
+                    get_branch_slug(slug) == slug
+                }
+        })
+        .map(|e| with_label(e, label.to_owned()))
+}
+
+const DELETE_DIRECTIVE: &str = "~delete";
+const REMOVE_DIRECTIVE: &str = "remove";
+const LIST_MERGE_MODES: [&str; 3] = ["append", "prepend", "replace"];
+
+fn format_key(path: &str, key: &serde_yaml::Value) -> String {
+    let key = match key {
+        serde_yaml::Value::String(key) => key.clone(),
+        key => serde_yaml::to_string(key)
+            .map(|key| key.trim_start_matches("---").trim().to_owned())
+            .unwrap_or_default(),
+    };
+    if path.is_empty() {
+        key
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// Gets the merge mode and items of a list override with the format `{ <mode>: [...] }`.
+fn get_list_merge(value: &serde_yaml::Mapping) -> Option<(&str, Vec<serde_yaml::Value>)> {
+    if value.len() != 1 {
+        return None;
+    }
+    let (mode, items) = value.iter().next().unwrap();
+    match (mode.as_str(), items) {
+        (Some(mode), serde_yaml::Value::Sequence(items)) if LIST_MERGE_MODES.contains(&mode) => {
+            Some((mode, items.clone()))
+        }
+        _ => None,
+    }
+}
+
+/// Applies the overrides in `b` to `a`. Maps are merged recursively and all other values are
+/// replaced, with the following exceptions:
+///
+/// - a map value of `~delete` removes the key from `a`
+/// - a `remove` key with a list value removes each of the listed keys from `a`
+/// - a list can be merged with `{ append: [...] }`, `{ prepend: [...] }` or `{ replace: [...] }`
+fn override_yaml(
+    a: &mut serde_yaml::Value,
+    b: serde_yaml::Value,
+    path: &str,
+) -> Result<(), String> {
     match (a, b) {
         (a @ &mut serde_yaml::Value::Mapping(_), serde_yaml::Value::Mapping(b)) => {
             let a = a.as_mapping_mut().unwrap();
             for (k, v) in b {
+                let key_path = format_key(path, &k);
+
+                if let (Some(REMOVE_DIRECTIVE), serde_yaml::Value::Sequence(keys)) =
+                    (k.as_str(), &v)
+                {
+                    for key in keys {
+                        if a.remove(key).is_none() {
+                            return Err(format!(
+                                "Unable to remove {} because it is not defined",
+                                format_key(path, key)
+                            ));
+                        }
+                    }
+                    continue;
+                }
+
+                if v.as_str() == Some(DELETE_DIRECTIVE) {
+                    if a.remove(&k).is_none() {
+                        return Err(format!(
+                            "Unable to delete {} because it is not defined",
+                            key_path
+                        ));
+                    }
+                    continue;
+                }
+
                 if !v.is_null() {
                     if a.contains_key(&k) {
-                        override_yaml(&mut a[&k], v);
+                        override_yaml(&mut a[&k], v, &key_path)?;
                     } else {
-                        a.insert(k.to_owned(), v.to_owned());
+                        let mut value = serde_yaml::Value::Null;
+                        override_yaml(&mut value, v, &key_path)?;
+                        a.insert(k, value);
                     }
                 }
             }
         }
+        (a, serde_yaml::Value::Mapping(b))
+            if (a.is_sequence() || a.is_null()) && get_list_merge(&b).is_some() =>
+        {
+            let (mode, items) = get_list_merge(&b).unwrap();
+            let mut list = match a {
+                serde_yaml::Value::Sequence(list) => list.clone(),
+                _ => Vec::new(),
+            };
+            match mode {
+                "append" => list.extend(items),
+                "prepend" => list = items.into_iter().chain(list).collect(),
+                _ => list = items,
+            }
+            *a = serde_yaml::Value::Sequence(list);
+        }
         (a, b) => *a = b,
     }
+    Ok(())
+}
+
+pub fn get_payment_source(
+    owner: &OwnerConfig,
+    payments: &PaymentsConfig,
+) -> Result<CreatorType, String> {
+    Ok(match payments {
+        PaymentsConfig::Owner => match owner {
+            OwnerConfig::Personal => CreatorType::User,
+            OwnerConfig::Group(_) => CreatorType::Group,
+        },
+        PaymentsConfig::Personal => CreatorType::User,
+        PaymentsConfig::Group => match owner {
+            OwnerConfig::Personal => {
+                return Err(
+                    "Cannot specify `payments: group` when owner is not a group.".to_owned(),
+                )
+            }
+            OwnerConfig::Group(_) => CreatorType::Group,
+        },
+    })
+}
+
+fn resolve_environment(
+    environments: &[EnvironmentConfig],
+    environment: &EnvironmentConfig,
+    chain: &mut Vec<String>,
+) -> Result<EnvironmentConfig, String> {
+    let parent_label = match &environment.extends {
+        Some(label) => label,
+        None => return Ok(environment.clone()),
+    };
+
+    chain.push(environment.label.clone());
+    if chain.contains(parent_label) {
+        return Err(format!(
+            "Environment inheritance cycle detected: {} -> {}",
+            chain.join(" -> "),
+            parent_label
+        ));
+    }
+
+    let parent = environments
+        .iter()
+        .find(|e| &e.label == parent_label)
+        .ok_or(format!(
+            "Environment {} extends unknown environment {}",
+            environment.label, parent_label
+        ))?;
+    let parent = resolve_environment(environments, parent, chain)?;
+
+    // Overrides are layered rather than merged so that directives like `~delete` are applied to
+    // the target instead of to the parent's overrides
+    let mut inherited_target_overrides = parent.inherited_target_overrides;
+    if let Some(overrides) = parent.target_overrides {
+        inherited_target_overrides.push((parent.label, overrides));
+    }
+
+    let mut variables = parent.variables;
+    variables.extend(environment.variables.clone());
+
+    Ok(EnvironmentConfig {
+        label: environment.label.clone(),
+        extends: environment.extends.clone(),
+        branches: if environment.branches.is_empty() {
+            parent.branches
+        } else {
+            environment.branches.clone()
+        },
+        tags: if environment.tags.is_empty() {
+            parent.tags
+        } else {
+            environment.tags.clone()
+        },
+        tag_commit: environment.tag_commit,
+        tag_template: environment.tag_template.clone().or(parent.tag_template),
+        tag_remote: environment.tag_remote.clone().or(parent.tag_remote),
+        protected: environment.protected,
+        protected_resources: environment.protected_resources.clone(),
+        owner: environment.owner.clone().or(parent.owner),
+        payments: environment.payments.clone().or(parent.payments),
+        target_name_prefix: environment
+            .target_name_prefix
+            .clone()
+            .or(parent.target_name_prefix),
+        target_access: environment.target_access.clone().or(parent.target_access),
+        target_overrides: environment.target_overrides.clone(),
+        inherited_target_overrides,
+        label_template: environment.label_template.clone(),
+        variables,
+        notify: environment.notify.clone(),
+    })
+}
+
+/// Applies the inherited configuration of each environment which `extends` another environment.
+pub fn resolve_environments(
+    environments: &[EnvironmentConfig],
+) -> Result<Vec<EnvironmentConfig>, String> {
+    environments
+        .iter()
+        .map(|environment| resolve_environment(environments, environment, &mut Vec::new()))
+        .collect()
 }
 
 fn get_target_config(
-    environment: EnvironmentConfig,
+    environment: &EnvironmentConfig,
     target: TargetConfig,
+    target_overrides: Vec<serde_yaml::Value>,
 ) -> Result<TargetConfig, String> {
     let target = match target {
         TargetConfig::Experience(mut experience) => {
             // Apply the name prefix to all places in the experience
-            if let Some(target_name_prefix) = environment.target_name_prefix {
+            if let Some(target_name_prefix) = environment.target_name_prefix.clone() {
                 let name_prefix = match target_name_prefix {
                     TargetNamePrefixConfig::Custom(prefix) => prefix,
                     TargetNamePrefixConfig::EnvironmentLabel => {
@@ -115,7 +476,7 @@ fn get_target_config(
             }
 
             // Apply the access to all places in the experience
-            if let Some(target_access) = environment.target_access {
+            if let Some(target_access) = environment.target_access.clone() {
                 let playability = Some(match target_access {
                     TargetAccessConfig::Public => PlayabilityTargetConfig::Public,
                     TargetAccessConfig::Private => PlayabilityTargetConfig::Private,
@@ -131,12 +492,19 @@ fn get_target_config(
                 }
             }
 
-            // Apply overrides last (they are the final trump)
-            if let Some(overrides) = environment.target_overrides {
-                let overrides = serde_yaml::to_value(overrides).unwrap();
+            // Apply overrides last (they are the final trump), starting with the overrides
+            // inherited from the furthest ancestor
+            if !target_overrides.is_empty() {
                 let mut as_value = serde_yaml::to_value(experience)
                     .map_err(|e| format!("Failed to serialize target: {}", e))?;
-                override_yaml(&mut as_value, overrides);
+                for overrides in target_overrides {
+                    override_yaml(&mut as_value, overrides, "").map_err(|e| {
+                        format!(
+                            "Unable to apply target overrides for environment {}: {}",
+                            environment.label, e
+                        )
+                    })?;
+                }
                 experience = serde_yaml::from_value::<ExperienceTargetConfig>(as_value)
                     .map_err(|e| format!("Failed to deserialize target: {}", e))?;
             };
@@ -147,14 +515,174 @@ fn get_target_config(
     Ok(target)
 }
 
+#[derive(Clone)]
+pub struct ProjectTarget {
+    /// The label of the target in the `targets` config, or `None` for the `target` shorthand.
+    pub label: Option<String>,
+    pub config: TargetConfig,
+}
+
+impl ProjectTarget {
+    /// Gets the ID of one of this target's resources within the environment's graph.
+    pub fn resource_id(&self, id: &str) -> String {
+        match &self.label {
+            Some(label) => format!("{}/{}", label, id),
+            None => id.to_owned(),
+        }
+    }
+
+    pub fn owns_resource(&self, resource_id: &str) -> bool {
+        match &self.label {
+            Some(label) => resource_id.starts_with(&format!("{}/", label)),
+            None => true,
+        }
+    }
+}
+
 pub struct Project {
     pub current_graph: ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
     pub state: ResourceStateVLatest,
     pub environment_config: EnvironmentConfig,
-    pub target_config: TargetConfig,
+    pub targets: Vec<ProjectTarget>,
     pub payment_source: CreatorType,
     pub state_config: StateConfig,
     pub owner_config: OwnerConfig,
+    /// The project's variables with the environment's values applied.
+    pub variables: BTreeMap<String, String>,
+}
+
+/// Gets the project's variables with the values of the environment's variables taking precedence.
+pub fn get_environment_variables(
+    config: &Config,
+    environment_config: &EnvironmentConfig,
+) -> BTreeMap<String, String> {
+    let mut variables = config.variables.clone();
+    variables.extend(environment_config.variables.clone());
+    variables
+}
+
+/// Gets the targets of the project for an environment, with variables resolved and the
+/// environment's configuration applied. Does not require network access.
+pub fn get_environment_targets(
+    project_path: PathBuf,
+    config: &Config,
+    environment_config: &EnvironmentConfig,
+) -> Result<Vec<ProjectTarget>, String> {
+    // Resolve variables before applying the environment's overrides so that both the target and
+    // its overrides can reference them
+    let variables = get_environment_variables(config, environment_config);
+    let mut interpolation_context =
+        InterpolationContext::new(project_path, &environment_config.label, variables)?;
+
+    // Ephemeral environments are defined in the config by their templated label
+    let config_label = environment_config
+        .label_template
+        .as_ref()
+        .unwrap_or(&environment_config.label);
+
+    // Overrides inherited from other environments are applied before the environment's own
+    let mut target_overrides = Vec::new();
+    for (label, overrides) in environment_config
+        .inherited_target_overrides
+        .iter()
+        .map(|(label, overrides)| (label, overrides))
+        .chain(
+            environment_config
+                .target_overrides
+                .as_ref()
+                .map(|overrides| (config_label, overrides)),
+        )
+    {
+        let environment_index = config
+            .environments
+            .iter()
+            .position(|e| &e.label == label)
+            .ok_or(format!(
+                "No environment configuration found with name {}",
+                label
+            ))?;
+        target_overrides.push(interpolation_context.interpolate(
+            overrides.clone(),
+            &format!("environments[{}].targetOverrides", environment_index),
+        )?);
+    }
+
+    let targets = match (config.target.is_null(), config.targets.is_empty()) {
+        (false, true) => {
+            let target_config =
+                interpolation_context.interpolate(config.target.clone(), "target")?;
+            let target_config = serde_yaml::from_value::<TargetConfig>(target_config)
+                .map_err(|e| format!("Unable to parse target config\n\t{}", e))?;
+            vec![ProjectTarget {
+                label: None,
+                config: get_target_config(environment_config, target_config, target_overrides)?,
+            }]
+        }
+        (true, false) => {
+            let mut target_overrides = target_overrides
+                .into_iter()
+                .map(|overrides| match overrides {
+                    serde_yaml::Value::Mapping(overrides) => Ok(overrides),
+                    _ => Err(format!(
+                        "Invalid environment {}: targetOverrides must be a map of target labels to overrides when using targets",
+                        environment_config.label
+                    )),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let mut targets = Vec::new();
+            for (label, target_config) in config.targets.iter() {
+                let target_config = interpolation_context
+                    .interpolate(target_config.clone(), &format!("targets.{}", label))?;
+                let target_config = serde_yaml::from_value::<TargetConfig>(target_config)
+                    .map_err(|e| format!("Unable to parse target config {}\n\t{}", label, e))?;
+                let label_key = serde_yaml::Value::String(label.clone());
+                let overrides = target_overrides
+                    .iter_mut()
+                    .filter_map(|overrides| overrides.remove(&label_key))
+                    .collect();
+                targets.push(ProjectTarget {
+                    label: Some(label.clone()),
+                    config: get_target_config(environment_config, target_config, overrides)?,
+                });
+            }
+
+            if let Some((label, _)) = target_overrides.into_iter().flatten().next() {
+                return Err(format!(
+                    "Invalid environment {}: targetOverrides contains unknown target {}",
+                    environment_config.label,
+                    label.as_str().unwrap_or_default()
+                ));
+            }
+
+            targets
+        }
+        (false, false) => {
+            return Err("Cannot specify both target and targets in the same config".to_owned())
+        }
+        (true, true) => return Err("No target or targets specified in config".to_owned()),
+    };
+
+    Ok(targets)
+}
+
+/// Gets the owner and payment source of the project for an environment.
+pub fn get_environment_owner(
+    config: &Config,
+    environment_config: &EnvironmentConfig,
+) -> Result<(OwnerConfig, CreatorType), String> {
+    let owner_config = environment_config
+        .owner
+        .clone()
+        .unwrap_or_else(|| config.owner.clone());
+    let payments_config = environment_config
+        .payments
+        .clone()
+        .unwrap_or_else(|| config.payments.clone());
+    let payment_source = get_payment_source(&owner_config, &payments_config)
+        .map_err(|e| format!("Invalid environment {}: {}", environment_config.label, e))?;
+
+    Ok((owner_config, payment_source))
 }
 
 pub async fn load_project(
@@ -162,13 +690,25 @@ pub async fn load_project(
     config: Config,
     environment: Option<&str>,
 ) -> Result<Option<Project>, String> {
+    let environments = resolve_environments(&config.environments)?;
+    let environment = match environment {
+        Some(label) => Some((label.to_owned(), "provided")),
+        None => get_env_var("MANTLE_ENVIRONMENT").map(|label| (label, "MANTLE_ENVIRONMENT")),
+    };
     let environment_config = match environment {
-        Some(label) => {
-            if let Some(result) = config.environments.iter().find(|d| d.label == label) {
-                logger::log(format!(
-                    "Selected provided environment configuration {}",
-                    Paint::cyan(label)
-                ));
+        Some((label, source)) => {
+            if let Some(result) = find_environment(&environments, &label) {
+                match source {
+                    "provided" => logger::log(format!(
+                        "Selected provided environment configuration {}",
+                        Paint::cyan(label)
+                    )),
+                    _ => logger::log(format!(
+                        "Selected environment configuration {} from the {} variable",
+                        Paint::cyan(label),
+                        Paint::cyan(source)
+                    )),
+                }
                 result
             } else {
                 return Err(format!(
@@ -178,61 +718,272 @@ pub async fn load_project(
             }
         }
         None => {
-            let current_branch = get_current_branch(project_path.clone())?;
-            if let Some(result) = config
-                .environments
-                .iter()
-                .find(|environment| match_branch(&current_branch, &environment.branches))
-            {
-                logger::log(format!(
-                    "Selected environment configuration {} because the current branch {} matched one of [{}]",
-                    Paint::cyan(result.label.clone()),
-                    Paint::cyan(current_branch),
-                    result.branches.iter().map(|b|Paint::cyan(b).to_string()).collect::<Vec<String>>().join(", ")
-                ));
-                result
-            } else {
-                logger::log(format!(
-                    "No environment configuration found for the current branch {}",
-                    Paint::cyan(current_branch)
-                ));
-                return Ok(None);
-            }
-        }
-    };
+            // CI providers often check out a detached HEAD for tags, so a missing branch is only
+            // an error if no environment matches the current tags either
+            let current_branch = get_current_branch(project_path.clone());
+            let current_tags = get_current_tags(project_path.clone());
 
-    let target_config = get_target_config(environment_config.clone(), config.target.clone())?;
+            let mut selected = None;
+            for environment in environments.iter() {
+                if let Ok(branch) = &current_branch {
+                    if match_patterns(branch, &environment.branches) {
+                        selected =
+                            Some((environment, "branch", branch.clone(), &environment.branches));
+                        break;
+                    }
+                }
+                // Ephemeral environments are created per branch, so tags only select environments
+                // with a fixed label
+                if is_ephemeral_environment(environment) {
+                    continue;
+                }
+                if let Some(tag) = current_tags
+                    .iter()
+                    .find(|tag| match_patterns(tag, &environment.tags))
+                {
+                    selected = Some((environment, "tag", tag.clone(), &environment.tags));
+                    break;
+                }
+            }
 
-    let payment_source = match config.payments {
-        PaymentsConfig::Owner => match config.owner {
-            OwnerConfig::Personal => CreatorType::User,
-            OwnerConfig::Group(_) => CreatorType::Group,
-        },
-        PaymentsConfig::Personal => CreatorType::User,
-        PaymentsConfig::Group => match config.owner {
-            OwnerConfig::Personal => {
-                return Err(
-                    "Cannot specify `payments: group` when owner is not a group.".to_owned(),
-                )
+            match (selected, current_branch) {
+                (Some((result, ref_type, ref_name, patterns)), _) => {
+                    let result = if ref_type == "branch" && is_ephemeral_environment(result) {
+                        with_label(
+                            result,
+                            get_branch_environment_label(&result.label, &ref_name),
+                        )
+                    } else {
+                        result.clone()
+                    };
+                    logger::log(format!(
+                        "Selected environment configuration {} because the current {} {} matched one of [{}]",
+                        Paint::cyan(result.label.clone()),
+                        ref_type,
+                        Paint::cyan(ref_name),
+                        patterns.iter().map(|b|Paint::cyan(b).to_string()).collect::<Vec<String>>().join(", ")
+                    ));
+                    result
+                }
+                (None, Ok(current_branch)) => {
+                    logger::log(format!(
+                        "No environment configuration found for the current branch {}",
+                        Paint::cyan(current_branch)
+                    ));
+                    return Ok(None);
+                }
+                (None, Err(e)) if current_tags.is_empty() => return Err(e),
+                (None, Err(_)) => {
+                    logger::log(format!(
+                        "No environment configuration found for the current tags {}",
+                        current_tags
+                            .iter()
+                            .map(|t| Paint::cyan(t).to_string())
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    ));
+                    return Ok(None);
+                }
             }
-            OwnerConfig::Group(_) => CreatorType::Group,
-        },
+        }
     };
 
+    let targets = get_environment_targets(project_path.clone(), &config, &environment_config)?;
+    let (owner_config, payment_source) = get_environment_owner(&config, &environment_config)?;
+
     // Get previous state
-    let state = get_previous_state(project_path.as_path(), &config, environment_config).await?;
+    let state = get_previous_state(project_path.as_path(), &config, &environment_config).await?;
 
     // Get our resource graphs
     let previous_graph =
         ResourceGraph::new(state.environments.get(&environment_config.label).unwrap());
 
+    let variables = get_environment_variables(&config, &environment_config);
+
     Ok(Some(Project {
         current_graph: previous_graph,
         state,
-        environment_config: environment_config.clone(),
-        target_config,
+        environment_config,
+        targets,
         payment_source,
         state_config: config.state.clone(),
-        owner_config: config.owner,
+        owner_config,
+        variables,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_environments(data: &str) -> Vec<EnvironmentConfig> {
+        serde_yaml::from_str(data).unwrap()
+    }
+
+    fn apply_override(a: &str, b: &str) -> Result<serde_yaml::Value, String> {
+        let mut a = serde_yaml::from_str(a).unwrap();
+        override_yaml(&mut a, serde_yaml::from_str(b).unwrap(), "")?;
+        Ok(a)
+    }
+
+    fn assert_override(a: &str, b: &str, expected: &str) {
+        assert_eq!(
+            apply_override(a, b).unwrap(),
+            serde_yaml::from_str::<serde_yaml::Value>(expected).unwrap()
+        );
+    }
+
+    #[test]
+    fn override_yaml_merges_maps() {
+        assert_override(
+            "{ configuration: { genre: all, maxPlayerCount: 10 }, icon: a.png }",
+            "{ configuration: { genre: building }, icon: b.png }",
+            "{ configuration: { genre: building, maxPlayerCount: 10 }, icon: b.png }",
+        );
+    }
+
+    #[test]
+    fn override_yaml_deletes_keys() {
+        assert_override(
+            "{ configuration: { genre: all, maxPlayerCount: 10 } }",
+            "{ configuration: { genre: ~delete } }",
+            "{ configuration: { maxPlayerCount: 10 } }",
+        );
+        assert_eq!(
+            apply_override(
+                "{ configuration: { maxPlayerCount: 10 } }",
+                "{ configuration: { genre: ~delete } }"
+            ),
+            Err("Unable to delete configuration.genre because it is not defined".to_owned())
+        );
+    }
+
+    #[test]
+    fn override_yaml_removes_keys() {
+        assert_override(
+            "{ products: { gems: {}, coins: {}, vip: {} } }",
+            "{ products: { remove: [gems, coins] } }",
+            "{ products: { vip: {} } }",
+        );
+        assert_eq!(
+            apply_override("{ products: {} }", "{ products: { remove: [gems] } }"),
+            Err("Unable to remove products.gems because it is not defined".to_owned())
+        );
+    }
+
+    #[test]
+    fn override_yaml_appends_to_lists() {
+        assert_override(
+            "{ thumbnails: [a.png, b.png] }",
+            "{ thumbnails: { append: [c.png] } }",
+            "{ thumbnails: [a.png, b.png, c.png] }",
+        );
+        assert_override(
+            "{}",
+            "{ thumbnails: { append: [c.png] } }",
+            "{ thumbnails: [c.png] }",
+        );
+    }
+
+    #[test]
+    fn override_yaml_prepends_to_lists() {
+        assert_override(
+            "{ thumbnails: [a.png, b.png] }",
+            "{ thumbnails: { prepend: [c.png] } }",
+            "{ thumbnails: [c.png, a.png, b.png] }",
+        );
+    }
+
+    #[test]
+    fn override_yaml_replaces_lists() {
+        assert_override(
+            "{ thumbnails: [a.png, b.png] }",
+            "{ thumbnails: { replace: [c.png] } }",
+            "{ thumbnails: [c.png] }",
+        );
+        assert_override(
+            "{ thumbnails: [a.png, b.png] }",
+            "{ thumbnails: [c.png] }",
+            "{ thumbnails: [c.png] }",
+        );
+    }
+
+    #[test]
+    fn resolve_environments_inherits_from_parent() {
+        let environments = resolve_environments(&get_environments(
+            "
+            - label: staging
+              branches: [dev]
+              variables: { a: '1', b: '2' }
+            - label: qa
+              extends: staging
+              variables: { b: '3' }
+            ",
+        ))
+        .unwrap();
+
+        assert_eq!(environments[1].branches, vec!["dev".to_owned()]);
+        assert_eq!(
+            environments[1].variables,
+            BTreeMap::from([
+                ("a".to_owned(), "1".to_owned()),
+                ("b".to_owned(), "3".to_owned())
+            ])
+        );
+    }
+
+    #[test]
+    fn resolve_environments_rejects_cycles() {
+        let result = resolve_environments(&get_environments(
+            "
+            - label: staging
+              extends: qa
+            - label: qa
+              extends: staging
+            ",
+        ));
+
+        assert_eq!(
+            result.err(),
+            Some("Environment inheritance cycle detected: staging -> qa -> staging".to_owned())
+        );
+    }
+
+    #[test]
+    fn find_environment_matches_templates() {
+        let environments = get_environments(
+            "
+            - label: production
+            - label: pr-{branch}
+            - label: pr-main
+            ",
+        );
+
+        let environment = find_environment(&environments, "pr-feature-new-shop").unwrap();
+        assert_eq!(environment.label, "pr-feature-new-shop");
+        assert_eq!(environment.label_template.as_deref(), Some("pr-{branch}"));
+
+        let environment = find_environment(&environments, "pr-main").unwrap();
+        assert_eq!(environment.label, "pr-main");
+        assert_eq!(environment.label_template, None);
+
+        assert!(find_environment(&environments, "pr-").is_none());
+        assert!(find_environment(&environments, "pr-Feature").is_none());
+        assert!(find_environment(&environments, "staging").is_none());
+    }
+
+    #[test]
+    fn get_branch_slug_normalizes_branches() {
+        assert_eq!(get_branch_slug("feature/new-shop"), "feature-new-shop");
+        assert_eq!(get_branch_slug("/feature//new_shop/"), "feature-new-shop");
+        assert_eq!(get_branch_slug("Feature/New-Shop"), "feature-new-shop");
+        assert_eq!(get_branch_slug("//"), "");
+
+        // Long branches are not shortened so that they can not collide
+        let branch = format!("feature/{}", "a".repeat(200));
+        assert_eq!(
+            get_branch_slug(&branch),
+            format!("feature-{}", "a".repeat(200))
+        );
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use rbx_api::{models::AssetId, RobloxApi};
+use yansi::Paint;
+
+use super::{
+    resource_graph::{Resource, ResourceGraph},
+    roblox_resource_manager::*,
+    state::get_hash,
+};
+
+type RobloxResourceGraph = ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>;
+
+/// Resources which are imported, renamed and deleted along with the resource they belong to.
+const CHILD_RESOURCE_TYPES: [(&str, &[&str]); 4] = [
+    ("place", &["placeFile", "placeConfiguration"]),
+    ("product", &["productIcon"]),
+    ("badge", &["badgeIcon"]),
+    ("assetAlias", &["asset"]),
+];
+
+fn split_id(resource_id: &str) -> (&str, &str) {
+    resource_id.split_once('_').unwrap_or((resource_id, ""))
+}
+
+/// Gets the display name used to match a resource to its configured counterpart.
+fn get_name(graph: &BTreeMap<String, RobloxResource>, resource_id: &str) -> Option<String> {
+    let resource = graph.get(resource_id)?;
+    match resource.get_inputs() {
+        RobloxInputs::Product(inputs) => Some(inputs.name),
+        RobloxInputs::Pass(inputs) => Some(inputs.name),
+        RobloxInputs::Badge(inputs) => Some(inputs.name),
+        RobloxInputs::Notification(inputs) => Some(inputs.name),
+        RobloxInputs::AssetAlias(inputs) => Some(inputs.name),
+        RobloxInputs::Place(_) => {
+            let (_, label) = split_id(resource_id);
+            match graph
+                .get(&format!("placeConfiguration_{}", label))
+                .map(|resource| resource.get_inputs())
+            {
+                Some(RobloxInputs::PlaceConfiguration(model)) => Some(model.name),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+struct Reconciler<'a> {
+    roblox_api: &'a RobloxApi,
+    imported: BTreeMap<String, RobloxResource>,
+    desired: BTreeMap<String, RobloxResource>,
+    /// Maps imported resource IDs to the IDs of the configured resources they were matched to.
+    renames: HashMap<String, String>,
+    /// Hashes of downloaded assets, cached since some assets are shared between resources.
+    hashes: HashMap<AssetId, String>,
+}
+
+impl<'a> Reconciler<'a> {
+    fn rename(&mut self, imported_id: &str, desired_id: &str) {
+        self.renames
+            .insert(imported_id.to_owned(), desired_id.to_owned());
+
+        let (resource_type, imported_label) = split_id(imported_id);
+        let (_, desired_label) = split_id(desired_id);
+        if let Some((_, child_types)) = CHILD_RESOURCE_TYPES
+            .iter()
+            .find(|(parent_type, _)| *parent_type == resource_type)
+        {
+            for child_type in child_types.iter() {
+                let imported_child_id = format!("{}_{}", child_type, imported_label);
+                if self.imported.contains_key(&imported_child_id) {
+                    self.renames.insert(
+                        imported_child_id,
+                        format!("{}_{}", child_type, desired_label),
+                    );
+                }
+            }
+        }
+    }
+
+    fn ids_of_type(graph: &BTreeMap<String, RobloxResource>, resource_type: &str) -> Vec<String> {
+        graph
+            .keys()
+            .filter(|id| split_id(id).0 == resource_type)
+            .cloned()
+            .collect()
+    }
+
+    /// Matches imported resources of the given type to configured resources with the same label
+    /// or display name.
+    fn match_by_name(&mut self, resource_type: &str) {
+        let mut unmatched_desired_ids = Self::ids_of_type(&self.desired, resource_type);
+        for imported_id in Self::ids_of_type(&self.imported, resource_type) {
+            let imported_label = split_id(&imported_id).1;
+            let imported_name = get_name(&self.imported, &imported_id);
+            // The start place can only be matched to the configured start place
+            let match_names = imported_name.is_some() && imported_label != "start";
+            let position = unmatched_desired_ids
+                .iter()
+                .position(|desired_id| split_id(desired_id).1 == imported_label)
+                .or_else(|| {
+                    unmatched_desired_ids.iter().position(|desired_id| {
+                        match_names
+                            && split_id(desired_id).1 != "start"
+                            && get_name(&self.desired, desired_id) == imported_name
+                    })
+                });
+            if let Some(position) = position {
+                let desired_id = unmatched_desired_ids.remove(position);
+                self.rename(&imported_id, &desired_id);
+            }
+        }
+    }
+
+    async fn get_asset_hash(&mut self, asset_id: AssetId) -> Result<String, String> {
+        if let Some(hash) = self.hashes.get(&asset_id) {
+            return Ok(hash.clone());
+        }
+        let data = self.roblox_api.download_asset(asset_id).await?;
+        let hash = get_hash(&data);
+        self.hashes.insert(asset_id, hash.clone());
+        Ok(hash)
+    }
+
+    /// Matches imported thumbnails to configured thumbnails with the same contents.
+    async fn match_thumbnails(&mut self) -> Result<(), String> {
+        let mut unmatched_desired_ids = Self::ids_of_type(&self.desired, "experienceThumbnail");
+        for imported_id in Self::ids_of_type(&self.imported, "experienceThumbnail") {
+            let asset_id = match self.imported[&imported_id].get_outputs() {
+                Some(RobloxOutputs::ExperienceThumbnail(outputs)) => outputs.asset_id,
+                _ => continue,
+            };
+            let hash = self.get_asset_hash(asset_id).await?;
+            let position = unmatched_desired_ids.iter().position(|desired_id| {
+                matches!(
+                    self.desired[desired_id].get_inputs(),
+                    RobloxInputs::ExperienceThumbnail(inputs) if inputs.file_hash == hash
+                )
+            });
+            if let Some(position) = position {
+                let desired_id = unmatched_desired_ids.remove(position);
+                self.rename(&imported_id, &desired_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the asset ID of the file a resource uploaded, which is downloaded to compare it with
+    /// the configured file.
+    fn get_file_asset_id(&self, resource: &RobloxResource) -> Option<AssetId> {
+        match resource.get_outputs()? {
+            RobloxOutputs::ExperienceThumbnail(outputs)
+            | RobloxOutputs::ProductIcon(outputs)
+            | RobloxOutputs::BadgeIcon(outputs)
+            | RobloxOutputs::AudioAsset(outputs) => Some(outputs.asset_id),
+            RobloxOutputs::ImageAsset(outputs) => Some(outputs.asset_id),
+            RobloxOutputs::Pass(outputs) => Some(outputs.icon_asset_id),
+            RobloxOutputs::PlaceFile(_) => {
+                let place_id = resource
+                    .get_dependencies()
+                    .into_iter()
+                    .find(|id| split_id(id).0 == "place")?;
+                match self.imported.get(&place_id)?.get_outputs()? {
+                    RobloxOutputs::Place(outputs) => Some(outputs.asset_id),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Gets the inputs for an imported resource which was matched to a configured resource. File
+    /// paths are taken from the configuration and file hashes from the downloaded files, so the
+    /// resource is only updated if its file changed. All other inputs are the imported ones.
+    async fn get_reconciled_inputs(
+        &mut self,
+        imported: &RobloxResource,
+        desired: &RobloxResource,
+    ) -> Result<RobloxInputs, String> {
+        let hash = match self.get_file_asset_id(imported) {
+            Some(asset_id) => Some(self.get_asset_hash(asset_id).await?),
+            None => None,
+        };
+
+        Ok(match (imported.get_inputs(), desired.get_inputs(), hash) {
+            (_, RobloxInputs::ExperienceThumbnail(inputs), Some(file_hash)) => {
+                RobloxInputs::ExperienceThumbnail(FileInputs {
+                    file_hash,
+                    ..inputs
+                })
+            }
+            (_, RobloxInputs::ProductIcon(inputs), Some(file_hash)) => {
+                RobloxInputs::ProductIcon(FileInputs {
+                    file_hash,
+                    ..inputs
+                })
+            }
+            (_, RobloxInputs::BadgeIcon(inputs), Some(file_hash)) => {
+                RobloxInputs::BadgeIcon(FileInputs {
+                    file_hash,
+                    ..inputs
+                })
+            }
+            (_, RobloxInputs::PlaceFile(inputs), Some(file_hash)) => {
+                RobloxInputs::PlaceFile(FileInputs {
+                    file_hash,
+                    ..inputs
+                })
+            }
+            (
+                RobloxInputs::ImageAsset(imported),
+                RobloxInputs::ImageAsset(inputs),
+                Some(file_hash),
+            ) => RobloxInputs::ImageAsset(FileWithGroupIdInputs {
+                file_hash,
+                group_id: imported.group_id,
+                ..inputs
+            }),
+            (
+                RobloxInputs::AudioAsset(imported),
+                RobloxInputs::AudioAsset(inputs),
+                Some(file_hash),
+            ) => RobloxInputs::AudioAsset(FileWithGroupIdInputs {
+                file_hash,
+                group_id: imported.group_id,
+                ..inputs
+            }),
+            (RobloxInputs::Pass(imported), RobloxInputs::Pass(inputs), Some(icon_file_hash)) => {
+                RobloxInputs::Pass(PassInputs {
+                    icon_file_path: inputs.icon_file_path,
+                    icon_file_hash,
+                    ..imported
+                })
+            }
+            (RobloxInputs::Badge(imported), RobloxInputs::Badge(inputs), _) => {
+                RobloxInputs::Badge(BadgeInputs {
+                    icon_file_path: inputs.icon_file_path,
+                    ..imported
+                })
+            }
+            (imported, _, _) => imported,
+        })
+    }
+
+    async fn reconcile(mut self) -> Result<RobloxResourceGraph, String> {
+        for resource_type in [
+            "place",
+            "product",
+            "pass",
+            "badge",
+            "notification",
+            "assetAlias",
+        ] {
+            self.match_by_name(resource_type);
+        }
+        self.match_thumbnails().await?;
+
+        // Never rename a resource to the ID of another imported resource which was not renamed
+        let renamed_ids: HashSet<String> = self
+            .renames
+            .iter()
+            .filter(|(imported_id, desired_id)| imported_id != desired_id)
+            .map(|(imported_id, _)| imported_id.clone())
+            .collect();
+        let imported = &self.imported;
+        self.renames.retain(|imported_id, desired_id| {
+            imported_id == desired_id
+                || !imported.contains_key(desired_id)
+                || renamed_ids.contains(desired_id)
+        });
+
+        let mut resources = Vec::new();
+        for (imported_id, imported) in self.imported.clone() {
+            let inputs = match self
+                .renames
+                .get(&imported_id)
+                .and_then(|desired_id| self.desired.get(desired_id))
+                .cloned()
+            {
+                Some(desired) => {
+                    logger::log(format!(
+                        "Matched {} to {}",
+                        Paint::cyan(&imported_id),
+                        Paint::cyan(desired.get_id())
+                    ));
+                    self.get_reconciled_inputs(&imported, &desired).await?
+                }
+                None => imported.get_inputs(),
+            };
+
+            let rename = |id: &String| self.renames.get(id).cloned().unwrap_or_else(|| id.clone());
+            let outputs = match imported.get_outputs() {
+                Some(outputs) => outputs,
+                None => continue,
+            };
+            let mut resource =
+                RobloxResource::existing(&rename(&imported_id), inputs, outputs, &[]);
+            for dependency_id in imported.get_dependencies() {
+                if let Some(dependency) = self.imported.get(&dependency_id) {
+                    // Only the dependency's ID is used
+                    resource.add_dependency(&RobloxResource::new(
+                        &rename(&dependency_id),
+                        dependency.get_inputs(),
+                        &[],
+                    ));
+                }
+            }
+            resources.push(resource);
+        }
+
+        Ok(ResourceGraph::new(&resources))
+    }
+}
+
+/// Matches the resources of an imported graph to the resources of the desired graph so that
+/// resources which are unchanged are not recreated or updated by the next deployment.
+///
+/// Places are matched by label (the imported place ID) or name; products, passes, badges,
+/// notifications and asset aliases by name; and thumbnails by their contents. Files are
+/// downloaded to compare them with the configured files. Resources which could not be matched
+/// are kept as they were imported.
+pub async fn reconcile_imported_graph(
+    roblox_api: &RobloxApi,
+    imported_graph: &RobloxResourceGraph,
+    desired_graph: &RobloxResourceGraph,
+) -> Result<RobloxResourceGraph, String> {
+    let to_map = |graph: &RobloxResourceGraph| {
+        graph
+            .get_resource_list()
+            .into_iter()
+            .map(|resource| (resource.get_id(), resource))
+            .collect::<BTreeMap<_, _>>()
+    };
+
+    Reconciler {
+        roblox_api,
+        imported: to_map(imported_graph),
+        desired: to_map(desired_graph),
+        renames: HashMap::new(),
+        hashes: HashMap::new(),
+    }
+    .reconcile()
+    .await
+}
@@ -1,6 +1,7 @@
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, BTreeSet},
     marker::PhantomData,
+    time::{Duration, Instant},
 };
 
 use async_trait::async_trait;
@@ -89,13 +90,71 @@ pub trait ResourceManager<TInputs, TOutputs> {
     ) -> Result<(), String>;
 }
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Serialize)]
 pub struct EvaluateResults {
     pub created_count: u32,
     pub updated_count: u32,
     pub deleted_count: u32,
     pub noop_count: u32,
     pub skipped_count: u32,
+    pub failed_count: u32,
+    pub resources: Vec<ResourceResult>,
+}
+
+impl EvaluateResults {
+    /// Converts the results into an error if any of the operations failed.
+    pub fn into_result(self) -> Result<EvaluateResults, String> {
+        if self.failed_count > 0 {
+            Err(format!(
+                "Failed {} changes(s) while evaluating the resource graph. See above for more details.",
+                self.failed_count
+            ))
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn add_resource(
+        &mut self,
+        resource_id: &str,
+        operation: ResourceOperation,
+        message: Option<String>,
+        price: Option<u32>,
+        duration: Duration,
+    ) {
+        self.resources.push(ResourceResult {
+            resource_id: resource_id.to_owned(),
+            operation,
+            message,
+            price,
+            duration_ms: duration.as_millis() as u64,
+        });
+    }
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceOperation {
+    Create,
+    Update,
+    Delete,
+    Noop,
+    Skip,
+    Fail,
+}
+
+#[derive(Clone, Serialize)]
+pub struct ResourceResult {
+    pub resource_id: ResourceId,
+    pub operation: ResourceOperation,
+    /// The reason a resource was skipped or the error it failed with.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// The number of Robux spent to create or update the resource.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<u32>,
+    /// How long the operation took, including any requests made to Roblox.
+    pub duration_ms: u64,
 }
 
 enum OperationResult<TOutputs> {
@@ -103,8 +162,8 @@ enum OperationResult<TOutputs> {
     Noop,
     Failed(String),
     SucceededDelete,
-    SucceededCreate(TOutputs),
-    SucceededUpdate(TOutputs),
+    SucceededCreate(TOutputs, Option<u32>),
+    SucceededUpdate(TOutputs, Option<u32>),
 }
 
 fn get_changeset(previous_hash: &str, new_hash: &str) -> Changeset {
@@ -119,7 +178,7 @@ where
 {
     phantom_inputs: std::marker::PhantomData<TInputs>,
     phantom_outputs: std::marker::PhantomData<TOutputs>,
-    resources: HashMap<ResourceId, TResource>,
+    resources: BTreeMap<ResourceId, TResource>,
 }
 
 impl<TResource, TInputs, TOutputs> ResourceGraph<TResource, TInputs, TOutputs>
@@ -156,7 +215,9 @@ where
     fn get_topological_order(&self) -> Result<Vec<ResourceId>, String> {
         let mut dependency_graph = self.get_dependency_graph();
 
-        let mut start_nodes: Vec<ResourceId> = dependency_graph
+        // Nodes which are ready to be visited are kept sorted so that ties are always broken by
+        // resource ID. This keeps the order (and therefore the state file) stable between runs.
+        let mut start_nodes: BTreeSet<ResourceId> = dependency_graph
             .iter()
             .filter_map(|(node, deps)| {
                 if deps.is_empty() {
@@ -168,13 +229,13 @@ where
             .collect();
 
         let mut ordered: Vec<ResourceId> = Vec::new();
-        while let Some(start_node) = start_nodes.pop() {
+        while let Some(start_node) = start_nodes.pop_first() {
             ordered.push(start_node.clone());
             for (node, deps) in dependency_graph.iter_mut() {
                 if deps.contains(&start_node) {
                     deps.retain(|dep| dep != &start_node);
                     if deps.is_empty() {
-                        start_nodes.push(node.clone());
+                        start_nodes.insert(node.clone());
                     }
                 }
             }
@@ -229,39 +290,54 @@ where
     fn handle_operation_result(
         &mut self,
         results: &mut EvaluateResults,
-        failures_count: &mut u32,
         previous_graph: &ResourceGraph<TResource, TInputs, TOutputs>,
         resource_id: &str,
         operation_result: OperationResult<TOutputs>,
+        duration: Duration,
     ) {
         // TODO: Improve DRY here
         match operation_result {
             OperationResult::SucceededDelete => {
                 // No need to update the graph since it's already not present
                 results.deleted_count += 1;
+                results.add_resource(resource_id, ResourceOperation::Delete, None, None, duration);
                 let previous_resource = previous_graph.resources.get(resource_id).unwrap();
                 logger::end_action_with_results(
                     "Succeeded with outputs:",
                     get_changeset(&previous_resource.get_outputs_hash(), ""),
                 );
             }
-            OperationResult::SucceededCreate(outputs) => {
+            OperationResult::SucceededCreate(outputs, price) => {
                 // Update the resource with the new outputs
                 let resource = self.resources.get_mut(resource_id).unwrap();
                 resource.set_outputs(outputs);
 
                 results.created_count += 1;
+                results.add_resource(
+                    resource_id,
+                    ResourceOperation::Create,
+                    None,
+                    price,
+                    duration,
+                );
                 logger::end_action_with_results(
                     "Succeeded with outputs:",
                     get_changeset("", &resource.get_outputs_hash()),
                 );
             }
-            OperationResult::SucceededUpdate(outputs) => {
+            OperationResult::SucceededUpdate(outputs, price) => {
                 // Update the resource with the new outputs
                 let resource = self.resources.get_mut(resource_id).unwrap();
                 resource.set_outputs(outputs);
 
                 results.updated_count += 1;
+                results.add_resource(
+                    resource_id,
+                    ResourceOperation::Update,
+                    None,
+                    price,
+                    duration,
+                );
                 let previous_resource = previous_graph.resources.get(resource_id).unwrap();
                 logger::end_action_with_results(
                     "Succeeded with outputs:",
@@ -283,6 +359,7 @@ where
                 );
 
                 results.noop_count += 1;
+                results.add_resource(resource_id, ResourceOperation::Noop, None, None, duration);
             }
             OperationResult::Skipped(reason) => {
                 // The resource was not evaluated. If the resource existed previously, we will copy
@@ -296,7 +373,14 @@ where
                 }
 
                 results.skipped_count += 1;
-                logger::end_action(format!("Skipped: {}", Paint::yellow(reason)));
+                logger::end_action(format!("Skipped: {}", Paint::yellow(&reason)));
+                results.add_resource(
+                    resource_id,
+                    ResourceOperation::Skip,
+                    Some(reason),
+                    None,
+                    duration,
+                );
             }
             OperationResult::Failed(error) => {
                 // An error occurred while creating or updating the resource. If the
@@ -309,8 +393,15 @@ where
                     self.resources.remove(resource_id);
                 }
 
-                *failures_count += 1;
-                logger::end_action(format!("Failed: {}", Paint::red(error)));
+                results.failed_count += 1;
+                logger::end_action(format!("Failed: {}", Paint::red(&error)));
+                results.add_resource(
+                    resource_id,
+                    ResourceOperation::Fail,
+                    Some(error),
+                    None,
+                    duration,
+                );
             }
         }
     }
@@ -446,7 +537,7 @@ where
                 .update(resource.get_inputs(), outputs, dependency_outputs, price)
                 .await
             {
-                Ok(outputs) => OperationResult::SucceededUpdate(outputs),
+                Ok(outputs) => OperationResult::SucceededUpdate(outputs, price),
                 Err(error) => OperationResult::Failed(error),
             }
         } else {
@@ -495,23 +586,64 @@ where
                 .create(resource.get_inputs(), dependency_outputs, price)
                 .await
             {
-                Ok(outputs) => OperationResult::SucceededCreate(outputs),
+                Ok(outputs) => OperationResult::SucceededCreate(outputs, price),
                 Err(error) => OperationResult::Failed(error),
             }
         }
     }
 
+    /// Creates, updates and deletes resources so that they match this graph. Operations that fail
+    /// are recorded in the returned results rather than returned as an error; use
+    /// [`EvaluateResults::into_result`] to treat them as one.
+    /// Returns the IDs of the resources and of every resource which depends on them, directly or
+    /// indirectly.
+    pub fn get_dependents(&self, resource_ids: &BTreeSet<ResourceId>) -> BTreeSet<ResourceId> {
+        let mut dependents = resource_ids.clone();
+        loop {
+            let dependents_count = dependents.len();
+            for (resource_id, resource) in self.resources.iter() {
+                if resource
+                    .get_dependencies()
+                    .iter()
+                    .any(|dependency| dependents.contains(dependency))
+                {
+                    dependents.insert(resource_id.clone());
+                }
+            }
+            if dependents.len() == dependents_count {
+                return dependents;
+            }
+        }
+    }
+
     pub async fn evaluate<TManager>(
         &mut self,
         previous_graph: &ResourceGraph<TResource, TInputs, TOutputs>,
         manager: &mut TManager,
         allow_purchases: bool,
     ) -> Result<EvaluateResults, String>
+    where
+        TManager: ResourceManager<TInputs, TOutputs>,
+    {
+        self.evaluate_resources(previous_graph, manager, allow_purchases, None)
+            .await
+    }
+
+    /// Evaluates the resources like [`ResourceGraph::evaluate`], but only creates or updates the
+    /// resources in `resource_ids` when it is provided. Other existing resources are copied from
+    /// the previous graph unchanged and counted as noops, so they are picked up by the next full
+    /// evaluation if they have changed.
+    pub async fn evaluate_resources<TManager>(
+        &mut self,
+        previous_graph: &ResourceGraph<TResource, TInputs, TOutputs>,
+        manager: &mut TManager,
+        allow_purchases: bool,
+        resource_ids: Option<&BTreeSet<ResourceId>>,
+    ) -> Result<EvaluateResults, String>
     where
         TManager: ResourceManager<TInputs, TOutputs>,
     {
         let mut results = EvaluateResults::default();
-        let mut failures_count: u32 = 0;
 
         // Iterate over previous resources in reverse order so that leaf resources are removed first
         let mut previous_resource_order = previous_graph.get_topological_order()?;
@@ -521,40 +653,53 @@ where
                 continue;
             }
 
+            let start = Instant::now();
             let operation_result: OperationResult<TOutputs> = self
                 .evaluate_delete(previous_graph, manager, resource_id)
                 .await;
             self.handle_operation_result(
                 &mut results,
-                &mut failures_count,
                 previous_graph,
                 resource_id,
                 operation_result,
+                start.elapsed(),
             );
         }
 
         let resource_order = self.get_topological_order()?;
         for resource_id in resource_order.iter() {
+            let start = Instant::now();
+            if let (Some(resource_ids), Some(previous_resource)) =
+                (resource_ids, previous_graph.resources.get(resource_id))
+            {
+                if !resource_ids.contains(resource_id) {
+                    self.resources
+                        .insert(resource_id.to_owned(), previous_resource.to_owned());
+                    results.noop_count += 1;
+                    results.add_resource(
+                        resource_id,
+                        ResourceOperation::Noop,
+                        None,
+                        None,
+                        start.elapsed(),
+                    );
+                    continue;
+                }
+            }
+
             let operation_result = self
                 .evaluate_create_or_update(previous_graph, manager, resource_id, allow_purchases)
                 .await;
             self.handle_operation_result(
                 &mut results,
-                &mut failures_count,
                 previous_graph,
                 resource_id,
                 operation_result,
+                start.elapsed(),
             );
         }
 
-        if failures_count > 0 {
-            Err(format!(
-                "Failed {} changes(s) while evaluating the resource graph. See above for more details.",
-                failures_count
-            ))
-        } else {
-            Ok(results)
-        }
+        Ok(results)
     }
 
     pub fn diff(
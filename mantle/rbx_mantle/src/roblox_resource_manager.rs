@@ -34,6 +34,7 @@ use rbxcloud::rbx::{
     v1::{PublishVersionType, RbxCloud},
 };
 use serde::{Deserialize, Serialize};
+use tokio::sync::OnceCell;
 use yansi::Paint;
 
 use super::resource_graph::{
@@ -153,6 +154,25 @@ pub enum RobloxInputs {
     Notification(NotificationInputs),
 }
 
+impl RobloxInputs {
+    /// Returns the paths of the files this resource is created from, relative to the project.
+    pub fn get_file_paths(&self) -> Vec<&str> {
+        match self {
+            RobloxInputs::ExperienceIcon(inputs)
+            | RobloxInputs::ExperienceThumbnail(inputs)
+            | RobloxInputs::PlaceFile(inputs)
+            | RobloxInputs::ProductIcon(inputs)
+            | RobloxInputs::BadgeIcon(inputs) => vec![&inputs.file_path],
+            RobloxInputs::ImageAsset(inputs) | RobloxInputs::AudioAsset(inputs) => {
+                vec![&inputs.file_path]
+            }
+            RobloxInputs::Pass(inputs) => vec![&inputs.icon_file_path],
+            RobloxInputs::Badge(inputs) => vec![&inputs.icon_file_path],
+            _ => Vec::new(),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ExperienceOutputs {
@@ -274,6 +294,24 @@ impl RobloxResource {
         self.dependencies.push(dependency.get_id());
         self
     }
+
+    /// Replaces the inputs of the resource while keeping its outputs and dependencies.
+    pub fn with_inputs(mut self, inputs: RobloxInputs) -> Self {
+        self.inputs = inputs;
+        self
+    }
+
+    /// Prefixes the IDs of the resource and its dependencies with `namespace` so that resources
+    /// from multiple targets can live in the same graph.
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.id = format!("{}/{}", namespace, self.id);
+        self.dependencies = self
+            .dependencies
+            .into_iter()
+            .map(|dependency| format!("{}/{}", namespace, dependency))
+            .collect();
+        self
+    }
 }
 
 impl Resource<RobloxInputs, RobloxOutputs> for RobloxResource {
@@ -326,16 +364,20 @@ impl Resource<RobloxInputs, RobloxOutputs> for RobloxResource {
     }
 }
 
-pub struct RobloxResourceManager {
-    roblox_api: RobloxApi,
-    roblox_cloud: Option<RbxCloud>,
-    project_path: PathBuf,
-    payment_source: CreatorType,
+/// An authenticated Roblox session, which is shared by every resource manager so that commands run
+/// for several projects only log in once.
+#[derive(Clone)]
+struct RobloxSession {
+    roblox_api: Arc<RobloxApi>,
+    roblox_cloud: Option<Arc<RbxCloud>>,
     user: GetAuthenticatedUserResponse,
 }
 
-impl RobloxResourceManager {
-    pub async fn new(project_path: &Path, payment_source: CreatorType) -> Result<Self, String> {
+// Projects run at the same time wait for the first of them to log in rather than logging in again
+static SESSION: OnceCell<RobloxSession> = OnceCell::const_new();
+
+impl RobloxSession {
+    async fn new() -> Result<Self, String> {
         let open_cloud_api_key = match env::var("MANTLE_OPEN_CLOUD_API_KEY") {
             Ok(v) => {
                 info!("Loaded cookie from ROBLOSECURITY environment variable.");
@@ -367,7 +409,36 @@ impl RobloxResourceManager {
             }
         };
 
-        let roblox_cloud = open_cloud_api_key.map(|api_key| RbxCloud::new(&api_key));
+        let roblox_cloud = open_cloud_api_key.map(|api_key| Arc::new(RbxCloud::new(&api_key)));
+
+        Ok(Self {
+            roblox_api: Arc::new(roblox_api),
+            roblox_cloud,
+            user,
+        })
+    }
+
+    /// Returns the session which was already logged in, or logs in.
+    async fn get() -> Result<Self, String> {
+        SESSION.get_or_try_init(Self::new).await.cloned()
+    }
+}
+
+pub struct RobloxResourceManager {
+    roblox_api: Arc<RobloxApi>,
+    roblox_cloud: Option<Arc<RbxCloud>>,
+    project_path: PathBuf,
+    payment_source: CreatorType,
+    user: GetAuthenticatedUserResponse,
+}
+
+impl RobloxResourceManager {
+    pub async fn new(project_path: &Path, payment_source: CreatorType) -> Result<Self, String> {
+        let RobloxSession {
+            roblox_api,
+            roblox_cloud,
+            user,
+        } = RobloxSession::get().await?;
 
         Ok(Self {
             roblox_api,
@@ -381,6 +452,18 @@ impl RobloxResourceManager {
     fn get_path<S: Into<String>>(&self, file: S) -> PathBuf {
         self.project_path.join(file.into())
     }
+
+    /// Downloads a published version of an asset with the manager's session.
+    pub async fn download_asset_version(
+        &self,
+        asset_id: AssetId,
+        version: u64,
+    ) -> Result<Vec<u8>, String> {
+        Ok(self
+            .roblox_api
+            .download_asset_version(asset_id, version)
+            .await?)
+    }
 }
 
 #[async_trait]
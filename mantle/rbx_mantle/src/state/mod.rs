@@ -12,14 +12,21 @@ use std::{
     ffi::OsStr,
     fs,
     path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
 };
 
-use chrono::Utc;
 use clap::crate_version;
 use rbx_api::{
+    asset_aliases::models::GetAssetAliasResponse,
+    badges::models::ListBadgeResponse,
+    developer_products::models::ListDeveloperProductResponseItem,
     experiences::models::GetExperienceResponse,
+    game_passes::models::GetGamePassResponse,
     models::{AssetId, CreatorType},
-    social_links::models::SocialLinkType,
+    notifications::models::ListNotificationResponse,
+    places::models::GetPlaceResponse,
+    social_links::models::{GetSocialLinkResponse, SocialLinkType},
     RobloxApi,
 };
 use rusoto_core::{HttpClient, Region};
@@ -35,7 +42,8 @@ use super::{
         AssetTargetConfig, Config, EnvironmentConfig, ExperienceTargetConfig, OwnerConfig,
         PlayabilityTargetConfig, RemoteStateConfig, StateConfig, TargetConfig,
     },
-    resource_graph::ResourceGraph,
+    project::ProjectTarget,
+    resource_graph::{Resource, ResourceGraph},
     roblox_resource_manager::*,
 };
 
@@ -74,12 +82,28 @@ fn get_state_file_path(project_path: &Path, key: Option<&str>) -> PathBuf {
     project_path.join(format!("{}.mantle-state.yml", key.unwrap_or_default()))
 }
 
-fn get_hash(data: &[u8]) -> String {
+pub(crate) fn get_hash(data: &[u8]) -> String {
     let digest = Sha256::digest(data);
     format!("{:x}", digest)
 }
 
+// Hashes of files which have already been read, keyed by path along with the file's modification
+// time, so that unchanged files are not read again when the desired graph is rebuilt (e.g. by
+// `deploy --watch`)
+static FILE_HASHES: Mutex<BTreeMap<PathBuf, (SystemTime, String)>> = Mutex::new(BTreeMap::new());
+
 fn get_file_hash(file_path: PathBuf) -> Result<String, String> {
+    let modified = fs::metadata(&file_path)
+        .and_then(|metadata| metadata.modified())
+        .ok();
+    if let (Some(modified), Ok(file_hashes)) = (modified, FILE_HASHES.lock()) {
+        if let Some((hashed_modified, hash)) = file_hashes.get(&file_path) {
+            if *hashed_modified == modified {
+                return Ok(hash.clone());
+            }
+        }
+    }
+
     let buffer = fs::read(&file_path).map_err(|e| {
         format!(
             "Failed to read file {} for hashing: {}",
@@ -87,7 +111,13 @@ fn get_file_hash(file_path: PathBuf) -> Result<String, String> {
             e
         )
     })?;
-    Ok(get_hash(&buffer))
+    let hash = get_hash(&buffer);
+
+    if let (Some(modified), Ok(mut file_hashes)) = (modified, FILE_HASHES.lock()) {
+        file_hashes.insert(file_path, (modified, hash.clone()));
+    }
+
+    Ok(hash)
 }
 
 fn parse_state(file_name: &str, data: &str) -> Result<ResourceState, String> {
@@ -201,6 +231,7 @@ pub async fn get_state_from_source(
         Some(ResourceState::Versioned(VersionedResourceState::V6(state))) => state,
         None => ResourceStateVLatest {
             environments: BTreeMap::new(),
+            deployed_at: BTreeMap::new(),
         },
     })
 }
@@ -232,11 +263,44 @@ pub async fn get_previous_state(
     Ok(state)
 }
 
-fn get_desired_experience_graph(
+/// Records the time an ephemeral environment was deployed so that `mantle gc` can find
+/// environments which have not been deployed recently. Other environments are not recorded so
+/// that their state does not change when nothing was deployed.
+pub fn record_deployment(state: &mut ResourceStateVLatest, environment_config: &EnvironmentConfig) {
+    if environment_config.label_template.is_none() {
+        return;
+    }
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    state
+        .deployed_at
+        .insert(environment_config.label.clone(), now);
+}
+
+pub(crate) const IMAGE_ASSET_EXTENSIONS: [&str; 6] = ["bmp", "gif", "jpeg", "jpg", "png", "tga"];
+pub(crate) const AUDIO_ASSET_EXTENSIONS: [&str; 2] = ["ogg", "mp3"];
+
+pub(crate) fn get_social_link_type(domain: &str) -> Option<SocialLinkType> {
+    match domain {
+        "facebook.com" => Some(SocialLinkType::Facebook),
+        "twitter.com" => Some(SocialLinkType::Twitter),
+        "youtube.com" => Some(SocialLinkType::YouTube),
+        "twitch.tv" => Some(SocialLinkType::Twitch),
+        "discord.gg" => Some(SocialLinkType::Discord),
+        "roblox.com" => Some(SocialLinkType::RobloxGroup),
+        "www.roblox.com" => Some(SocialLinkType::RobloxGroup),
+        "guilded.gg" => Some(SocialLinkType::Guilded),
+        _ => None,
+    }
+}
+
+fn get_desired_experience_resources(
     project_path: &Path,
     target_config: &ExperienceTargetConfig,
     owner_config: &OwnerConfig,
-) -> Result<ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>, String> {
+) -> Result<Vec<RobloxResource>, String> {
     let mut resources: Vec<RobloxResource> = Vec::new();
 
     let group_id = match owner_config {
@@ -352,22 +416,10 @@ fn get_desired_experience_graph(
                 "Unknown social link type for URL {}",
                 social_link.url
             ))?;
-            let link_type = match domain {
-                "facebook.com" => SocialLinkType::Facebook,
-                "twitter.com" => SocialLinkType::Twitter,
-                "youtube.com" => SocialLinkType::YouTube,
-                "twitch.tv" => SocialLinkType::Twitch,
-                "discord.gg" => SocialLinkType::Discord,
-                "roblox.com" => SocialLinkType::RobloxGroup,
-                "www.roblox.com" => SocialLinkType::RobloxGroup,
-                "guilded.gg" => SocialLinkType::Guilded,
-                domain => {
-                    return Err(format!(
-                        "Unknown social link type for domain name {}",
-                        domain
-                    ))
-                }
-            };
+            let link_type = get_social_link_type(domain).ok_or(format!(
+                "Unknown social link type for domain name {}",
+                domain
+            ))?;
             resources.push(RobloxResource::new(
                 &format!("socialLink_{}", domain),
                 RobloxInputs::SocialLink(SocialLinkInputs {
@@ -488,19 +540,21 @@ fn get_desired_experience_graph(
             };
 
             for (file, alias) in assets {
-                let resource_inputs = match Path::new(&file).extension().map(OsStr::to_str) {
-                    Some(Some("bmp" | "gif" | "jpeg" | "jpg" | "png" | "tga")) => {
+                let resource_inputs = match Path::new(&file).extension().and_then(OsStr::to_str) {
+                    Some(extension) if IMAGE_ASSET_EXTENSIONS.contains(&extension) => {
                         RobloxInputs::ImageAsset(FileWithGroupIdInputs {
                             file_path: file.clone(),
                             file_hash: get_file_hash(project_path.join(&file))?,
                             group_id,
                         })
                     }
-                    Some(Some("ogg" | "mp3")) => RobloxInputs::AudioAsset(FileWithGroupIdInputs {
-                        file_path: file.clone(),
-                        file_hash: get_file_hash(project_path.join(&file))?,
-                        group_id,
-                    }),
+                    Some(extension) if AUDIO_ASSET_EXTENSIONS.contains(&extension) => {
+                        RobloxInputs::AudioAsset(FileWithGroupIdInputs {
+                            file_path: file.clone(),
+                            file_hash: get_file_hash(project_path.join(&file))?,
+                            group_id,
+                        })
+                    }
                     _ => return Err(format!("Unable to determine asset type for file: {}", file)),
                 };
 
@@ -552,21 +606,301 @@ fn get_desired_experience_graph(
         }
     }
 
-    Ok(ResourceGraph::new(&resources))
+    Ok(resources)
+}
+
+fn get_desired_target_resources(
+    project_path: &Path,
+    target: &ProjectTarget,
+    owner_config: &OwnerConfig,
+) -> Result<Vec<RobloxResource>, String> {
+    let resources = match &target.config {
+        TargetConfig::Experience(experience_target_config) => {
+            get_desired_experience_resources(project_path, experience_target_config, owner_config)
+        }
+    }
+    .map_err(|e| match &target.label {
+        Some(label) => format!("Invalid target {}: {}", label, e),
+        None => e,
+    })?;
+
+    Ok(match &target.label {
+        Some(label) => resources
+            .into_iter()
+            .map(|resource| resource.with_namespace(label))
+            .collect(),
+        None => resources,
+    })
 }
 
 pub fn get_desired_graph(
     project_path: &Path,
-    target_config: &TargetConfig,
+    targets: &[ProjectTarget],
     owner_config: &OwnerConfig,
 ) -> Result<ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>, String> {
-    match target_config {
-        TargetConfig::Experience(experience_target_config) => {
-            get_desired_experience_graph(project_path, experience_target_config, owner_config)
+    let mut resources: Vec<RobloxResource> = Vec::new();
+    for target in targets {
+        resources.extend(get_desired_target_resources(
+            project_path,
+            target,
+            owner_config,
+        )?);
+    }
+
+    Ok(ResourceGraph::new(&resources))
+}
+
+/// Gets the desired graph for only the target with the given label. The resources of all other
+/// targets are carried over from `previous_graph` unchanged so that they are left as-is.
+pub fn get_target_desired_graph(
+    project_path: &Path,
+    targets: &[ProjectTarget],
+    target_label: &str,
+    owner_config: &OwnerConfig,
+    previous_graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+) -> Result<ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>, String> {
+    let target = targets
+        .iter()
+        .find(|target| target.label.as_deref() == Some(target_label))
+        .ok_or(format!("No target found with label {}", target_label))?;
+
+    let mut resources = get_desired_target_resources(project_path, target, owner_config)?;
+    resources.extend(
+        previous_graph
+            .get_resource_list()
+            .into_iter()
+            .filter(|resource| !target.owns_resource(&resource.get_id())),
+    );
+
+    Ok(ResourceGraph::new(&resources))
+}
+
+fn get_imported_place_resources(
+    label: &str,
+    place: GetPlaceResponse,
+    experience: &RobloxResource,
+) -> Vec<RobloxResource> {
+    let place_resource = RobloxResource::existing(
+        &format!("place_{}", label),
+        RobloxInputs::Place(PlaceInputs {
+            is_start: place.is_root_place,
+        }),
+        RobloxOutputs::Place(AssetOutputs { asset_id: place.id }),
+        &[experience],
+    );
+
+    vec![
+        RobloxResource::existing(
+            &format!("placeFile_{}", label),
+            RobloxInputs::PlaceFile(FileInputs {
+                file_path: "fake-path".to_owned(),
+                file_hash: "fake-hash".to_owned(),
+            }),
+            RobloxOutputs::PlaceFile(PlaceFileOutputs {
+                version: place.current_saved_version,
+            }),
+            &[&place_resource, experience],
+        ),
+        RobloxResource::existing(
+            &format!("placeConfiguration_{}", label),
+            RobloxInputs::PlaceConfiguration(place.into()),
+            RobloxOutputs::PlaceConfiguration,
+            &[&place_resource],
+        ),
+        place_resource,
+    ]
+}
+
+fn get_imported_social_link_resource(
+    social_link: GetSocialLinkResponse,
+    experience: &RobloxResource,
+) -> Result<RobloxResource, String> {
+    let domain = social_link
+        .url
+        .domain()
+        .ok_or_else(|| "Invalid social link URL".to_owned())?;
+    Ok(RobloxResource::existing(
+        &format!("socialLink_{}", domain),
+        RobloxInputs::SocialLink(SocialLinkInputs {
+            title: social_link.title,
+            url: social_link.url.to_string(),
+            link_type: social_link.link_type,
+        }),
+        RobloxOutputs::SocialLink(AssetOutputs {
+            asset_id: social_link.id,
+        }),
+        &[experience],
+    ))
+}
+
+fn get_imported_product_resources(
+    label: &str,
+    product: ListDeveloperProductResponseItem,
+    experience: &RobloxResource,
+) -> Vec<RobloxResource> {
+    let mut resources = Vec::new();
+
+    let product_resource = RobloxResource::existing(
+        &format!("product_{}", label),
+        RobloxInputs::Product(ProductInputs {
+            name: product.name,
+            description: product.description.unwrap_or_default(),
+            price: product.price_in_robux,
+        }),
+        RobloxOutputs::Product(ProductOutputs {
+            asset_id: product.product_id,
+            product_id: product.developer_product_id,
+        }),
+        &[experience],
+    );
+    if let Some(icon_id) = product.icon_image_asset_id {
+        resources.push(RobloxResource::existing(
+            &format!("productIcon_{}", label),
+            RobloxInputs::ProductIcon(FileInputs {
+                file_path: "fake-path".to_owned(),
+                file_hash: "fake-hash".to_owned(),
+            }),
+            RobloxOutputs::ProductIcon(AssetOutputs { asset_id: icon_id }),
+            &[&product_resource],
+        ));
+    }
+    resources.push(product_resource);
+
+    resources
+}
+
+fn get_imported_pass_resource(
+    label: &str,
+    pass: GetGamePassResponse,
+    experience: &RobloxResource,
+) -> RobloxResource {
+    RobloxResource::existing(
+        &format!("pass_{}", label),
+        RobloxInputs::Pass(PassInputs {
+            name: pass.name,
+            description: pass.description,
+            price: pass.price_in_robux,
+            icon_file_path: "fake-path".to_owned(),
+            icon_file_hash: "fake-hash".to_owned(),
+        }),
+        RobloxOutputs::Pass(PassOutputs {
+            asset_id: pass.target_id,
+            icon_asset_id: pass.icon_image_asset_id,
+        }),
+        &[experience],
+    )
+}
+
+fn get_imported_badge_resources(
+    label: &str,
+    badge: ListBadgeResponse,
+    experience: &RobloxResource,
+) -> Vec<RobloxResource> {
+    let badge_resource = RobloxResource::existing(
+        &format!("badge_{}", label),
+        RobloxInputs::Badge(BadgeInputs {
+            name: badge.name,
+            description: badge.description.unwrap_or_default(),
+            enabled: badge.enabled,
+            icon_file_path: "fake-path".to_owned(),
+        }),
+        RobloxOutputs::Badge(AssetWithInitialIconOutputs {
+            asset_id: badge.id,
+            initial_icon_asset_id: badge.icon_image_id,
+        }),
+        &[experience],
+    );
+
+    vec![
+        RobloxResource::existing(
+            &format!("badgeIcon_{}", label),
+            RobloxInputs::BadgeIcon(FileInputs {
+                file_path: "fake-path".to_owned(),
+                file_hash: "fake-hash".to_owned(),
+            }),
+            RobloxOutputs::BadgeIcon(AssetOutputs {
+                asset_id: badge.icon_image_id,
+            }),
+            &[&badge_resource],
+        ),
+        badge_resource,
+    ]
+}
+
+/// Returns no resources for asset types which Mantle does not manage.
+fn get_imported_asset_resources(
+    label: &str,
+    asset: GetAssetAliasResponse,
+    group_id: Option<AssetId>,
+    experience: &RobloxResource,
+) -> Vec<RobloxResource> {
+    let resource_data = match asset.asset.type_id {
+        1 => Some((
+            RobloxInputs::ImageAsset(FileWithGroupIdInputs {
+                file_path: "fake-path".to_owned(),
+                file_hash: "fake-hash".to_owned(),
+                group_id,
+            }),
+            RobloxOutputs::ImageAsset(ImageAssetOutputs {
+                asset_id: asset.target_id,
+                decal_asset_id: None,
+            }),
+        )),
+        3 => Some((
+            RobloxInputs::AudioAsset(FileWithGroupIdInputs {
+                file_path: "fake-path".to_owned(),
+                file_hash: "fake-hash".to_owned(),
+                group_id,
+            }),
+            RobloxOutputs::AudioAsset(AssetOutputs {
+                asset_id: asset.target_id,
+            }),
+        )),
+        _ => None,
+    };
+
+    match resource_data {
+        Some((resource_inputs, resource_outputs)) => {
+            let asset_resource = RobloxResource::existing(
+                &format!("asset_{}", label),
+                resource_inputs,
+                resource_outputs,
+                &[],
+            );
+            vec![
+                RobloxResource::existing(
+                    &format!("assetAlias_{}", label),
+                    RobloxInputs::AssetAlias(AssetAliasInputs {
+                        name: asset.name.clone(),
+                    }),
+                    RobloxOutputs::AssetAlias(AssetAliasOutputs { name: asset.name }),
+                    &[experience, &asset_resource],
+                ),
+                asset_resource,
+            ]
         }
+        None => Vec::new(),
     }
 }
 
+fn get_imported_notification_resource(
+    label: &str,
+    notification: ListNotificationResponse,
+    experience: &RobloxResource,
+) -> RobloxResource {
+    RobloxResource::existing(
+        &format!("notification_{}", label),
+        RobloxInputs::Notification(NotificationInputs {
+            name: notification.name,
+            content: notification.content,
+        }),
+        RobloxOutputs::Notification(NotificationOutputs {
+            id: notification.id,
+        }),
+        &[experience],
+    )
+}
+
 pub async fn import_graph(
     roblox_api: &RobloxApi,
     target_id: AssetId,
@@ -648,189 +982,51 @@ pub async fn import_graph(
     logger::log("Importing places");
     let places = roblox_api.get_all_places(target_id).await?;
     for place in places {
-        let resource_id = if place.is_root_place {
+        let label = if place.is_root_place {
             "start".to_owned()
         } else {
             place.id.to_string()
         };
-
-        let place_resource = RobloxResource::existing(
-            &format!("place_{}", resource_id),
-            RobloxInputs::Place(PlaceInputs {
-                is_start: place.is_root_place,
-            }),
-            RobloxOutputs::Place(AssetOutputs { asset_id: place.id }),
-            &[&experience],
-        );
-        resources.push(place_resource.clone());
-
-        resources.push(RobloxResource::existing(
-            &format!("placeFile_{}", resource_id),
-            RobloxInputs::PlaceFile(FileInputs {
-                file_path: "fake-path".to_owned(),
-                file_hash: "fake-hash".to_owned(),
-            }),
-            RobloxOutputs::PlaceFile(PlaceFileOutputs {
-                version: place.current_saved_version,
-            }),
-            &[&place_resource, &experience],
-        ));
-
-        resources.push(RobloxResource::existing(
-            &format!("placeConfiguration_{}", resource_id),
-            RobloxInputs::PlaceConfiguration(place.into()),
-            RobloxOutputs::PlaceConfiguration,
-            &[&place_resource],
-        ));
+        resources.extend(get_imported_place_resources(&label, place, &experience));
     }
 
     logger::log("Importing social links");
     let social_links = roblox_api.list_social_links(target_id).await?;
     for social_link in social_links {
-        let domain = social_link
-            .url
-            .domain()
-            .ok_or_else(|| "Invalid social link URL".to_owned())?;
-        resources.push(RobloxResource::existing(
-            &format!("socialLink_{}", domain),
-            RobloxInputs::SocialLink(SocialLinkInputs {
-                title: social_link.title,
-                url: social_link.url.to_string(),
-                link_type: social_link.link_type,
-            }),
-            RobloxOutputs::SocialLink(AssetOutputs {
-                asset_id: social_link.id,
-            }),
-            &[&experience],
-        ));
+        resources.push(get_imported_social_link_resource(social_link, &experience)?);
     }
 
     logger::log("Importing products");
     let developer_products = roblox_api.get_all_developer_products(target_id).await?;
     for product in developer_products {
-        let product_resource = RobloxResource::existing(
-            &format!("product_{}", product.product_id),
-            RobloxInputs::Product(ProductInputs {
-                name: product.name,
-                description: product.description.unwrap_or_default(),
-                price: product.price_in_robux,
-            }),
-            RobloxOutputs::Product(ProductOutputs {
-                asset_id: product.product_id,
-                product_id: product.developer_product_id,
-            }),
-            &[&experience],
-        );
-        if let Some(icon_id) = product.icon_image_asset_id {
-            resources.push(RobloxResource::existing(
-                &format!("productIcon_{}", product.product_id),
-                RobloxInputs::ProductIcon(FileInputs {
-                    file_path: "fake-path".to_owned(),
-                    file_hash: "fake-hash".to_owned(),
-                }),
-                RobloxOutputs::ProductIcon(AssetOutputs { asset_id: icon_id }),
-                &[&product_resource],
-            ));
-        }
-        resources.push(product_resource);
+        let label = product.product_id.to_string();
+        resources.extend(get_imported_product_resources(&label, product, &experience));
     }
 
     logger::log("Importing passes");
     let game_passes = roblox_api.get_all_game_passes(target_id).await?;
     for pass in game_passes {
-        resources.push(RobloxResource::existing(
-            &format!("pass_{}", pass.target_id),
-            RobloxInputs::Pass(PassInputs {
-                name: pass.name,
-                description: pass.description,
-                price: pass.price_in_robux,
-                icon_file_path: "fake-path".to_owned(),
-                icon_file_hash: "fake-hash".to_owned(),
-            }),
-            RobloxOutputs::Pass(PassOutputs {
-                asset_id: pass.target_id,
-                icon_asset_id: pass.icon_image_asset_id,
-            }),
-            &[&experience],
-        ));
+        let label = pass.target_id.to_string();
+        resources.push(get_imported_pass_resource(&label, pass, &experience));
     }
 
     logger::log("Importing badges");
     let badges = roblox_api.get_all_badges(target_id).await?;
     for badge in badges {
-        let badge_resource = RobloxResource::existing(
-            &format!("badge_{}", badge.id),
-            RobloxInputs::Badge(BadgeInputs {
-                name: badge.name,
-                description: badge.description.unwrap_or_default(),
-                enabled: badge.enabled,
-                icon_file_path: "fake-path".to_owned(),
-            }),
-            RobloxOutputs::Badge(AssetWithInitialIconOutputs {
-                asset_id: badge.id,
-                initial_icon_asset_id: badge.icon_image_id,
-            }),
-            &[&experience],
-        );
-        resources.push(RobloxResource::existing(
-            &format!("badgeIcon_{}", badge.id),
-            RobloxInputs::BadgeIcon(FileInputs {
-                file_path: "fake-path".to_owned(),
-                file_hash: "fake-hash".to_owned(),
-            }),
-            RobloxOutputs::BadgeIcon(AssetOutputs {
-                asset_id: badge.icon_image_id,
-            }),
-            &[&badge_resource],
-        ));
-        resources.push(badge_resource);
+        let label = badge.id.to_string();
+        resources.extend(get_imported_badge_resources(&label, badge, &experience));
     }
 
     logger::log("Importing assets");
     let assets = roblox_api.get_all_asset_aliases(target_id).await?;
     for asset in assets {
-        let resource_data = match asset.asset.type_id {
-            1 => Some((
-                RobloxInputs::ImageAsset(FileWithGroupIdInputs {
-                    file_path: "fake-path".to_owned(),
-                    file_hash: "fake-hash".to_owned(),
-                    group_id,
-                }),
-                RobloxOutputs::ImageAsset(ImageAssetOutputs {
-                    asset_id: asset.target_id,
-                    decal_asset_id: None,
-                }),
-            )),
-            3 => Some((
-                RobloxInputs::AudioAsset(FileWithGroupIdInputs {
-                    file_path: "fake-path".to_owned(),
-                    file_hash: "fake-hash".to_owned(),
-                    group_id,
-                }),
-                RobloxOutputs::AudioAsset(AssetOutputs {
-                    asset_id: asset.target_id,
-                }),
-            )),
-            _ => None,
-        };
-
-        if let Some((resource_inputs, resource_outputs)) = resource_data {
-            let asset_resource = RobloxResource::existing(
-                &format!("asset_{}", asset.name),
-                resource_inputs,
-                resource_outputs,
-                &[],
-            );
-            resources.push(RobloxResource::existing(
-                &format!("assetAlias_{}", asset.name),
-                RobloxInputs::AssetAlias(AssetAliasInputs {
-                    name: asset.name.clone(),
-                }),
-                RobloxOutputs::AssetAlias(AssetAliasOutputs { name: asset.name }),
-                &[&experience, &asset_resource],
-            ));
-            resources.push(asset_resource);
-        }
+        let label = asset.name.clone();
+        resources.extend(get_imported_asset_resources(
+            &label,
+            asset,
+            group_id,
+            &experience,
+        ));
     }
 
     logger::log("Importing spatial voice settings");
@@ -850,22 +1046,205 @@ pub async fn import_graph(
     logger::log("Importing notifications");
     let notifications = roblox_api.get_all_notifications(target_id).await?;
     for notification in notifications {
-        resources.push(RobloxResource::existing(
-            &format!("notification_{}", notification.name),
-            RobloxInputs::Notification(NotificationInputs {
-                name: notification.name,
-                content: notification.content,
-            }),
-            RobloxOutputs::Notification(NotificationOutputs {
-                id: notification.id,
-            }),
-            &[&experience],
+        let label = notification.name.clone();
+        resources.push(get_imported_notification_resource(
+            &label,
+            notification,
+            &experience,
         ));
     }
 
     Ok(ResourceGraph::new(&resources))
 }
 
+fn parse_roblox_id(roblox_id: &str) -> Result<AssetId, String> {
+    roblox_id
+        .parse::<AssetId>()
+        .map_err(|e| format!("Roblox ID {} is invalid: {}", roblox_id, e))
+}
+
+/// Imports a single existing Roblox resource (e.g. `product_gems500`) into an environment's graph.
+/// Resources of labelled targets are prefixed with the target's label (e.g. `game/pass_vip`).
+/// Returns the imported resources, which include any resources the resource owns (e.g. a
+/// product's icon).
+pub async fn import_resource(
+    roblox_api: &RobloxApi,
+    graph: &ResourceGraph<RobloxResource, RobloxInputs, RobloxOutputs>,
+    target: &ProjectTarget,
+    resource_id: &str,
+    roblox_id: &str,
+) -> Result<Vec<RobloxResource>, String> {
+    let local_id = match &target.label {
+        Some(label) => resource_id
+            .strip_prefix(&format!("{}/", label))
+            .unwrap_or(resource_id),
+        None => resource_id,
+    };
+    let (resource_type, label) = local_id
+        .split_once('_')
+        .filter(|(_, label)| !label.is_empty())
+        .ok_or(format!("Invalid resource ID {}", resource_id))?;
+
+    let experience_id = target.resource_id("experience_singleton");
+    let (experience_asset_id, start_place_id) = match graph.get_outputs(&experience_id) {
+        Some(RobloxOutputs::Experience(outputs)) => (outputs.asset_id, outputs.start_place_id),
+        _ => {
+            return Err(
+                "The environment has no experience: import or deploy the experience first"
+                    .to_owned(),
+            )
+        }
+    };
+    // A stand-in for the experience so that dependencies are created before namespacing
+    let experience = RobloxResource::existing(
+        "experience_singleton",
+        RobloxInputs::Experience(ExperienceInputs { group_id: None }),
+        RobloxOutputs::Experience(ExperienceOutputs {
+            asset_id: experience_asset_id,
+            start_place_id,
+        }),
+        &[],
+    );
+
+    let not_found = || {
+        format!(
+            "No {} found with ID {} in experience {}",
+            resource_type, roblox_id, experience_asset_id
+        )
+    };
+
+    let resources = match resource_type {
+        "place" => {
+            let place_id = parse_roblox_id(roblox_id)?;
+            let place = roblox_api
+                .get_all_places(experience_asset_id)
+                .await?
+                .into_iter()
+                .find(|place| place.id == place_id)
+                .ok_or_else(not_found)?;
+            if place.is_root_place != (label == "start") {
+                return Err(
+                    "The experience's start place can only be imported as place_start".to_owned(),
+                );
+            }
+            get_imported_place_resources(label, place, &experience)
+        }
+        "socialLink" => {
+            let social_link_id = parse_roblox_id(roblox_id)?;
+            let social_link = roblox_api
+                .list_social_links(experience_asset_id)
+                .await?
+                .into_iter()
+                .find(|social_link| social_link.id == social_link_id)
+                .ok_or_else(not_found)?;
+            if social_link.url.domain() != Some(label) {
+                return Err(format!(
+                    "Social link {} has the URL {}: import it as socialLink_{}",
+                    roblox_id,
+                    social_link.url,
+                    social_link.url.domain().unwrap_or_default()
+                ));
+            }
+            vec![get_imported_social_link_resource(social_link, &experience)?]
+        }
+        "product" => {
+            let product_id = parse_roblox_id(roblox_id)?;
+            let product = roblox_api
+                .get_all_developer_products(experience_asset_id)
+                .await?
+                .into_iter()
+                .find(|product| {
+                    product.product_id == product_id || product.developer_product_id == product_id
+                })
+                .ok_or_else(not_found)?;
+            get_imported_product_resources(label, product, &experience)
+        }
+        "pass" => {
+            let pass_id = parse_roblox_id(roblox_id)?;
+            let pass = roblox_api
+                .get_all_game_passes(experience_asset_id)
+                .await?
+                .into_iter()
+                .find(|pass| pass.target_id == pass_id)
+                .ok_or_else(not_found)?;
+            vec![get_imported_pass_resource(label, pass, &experience)]
+        }
+        "badge" => {
+            let badge_id = parse_roblox_id(roblox_id)?;
+            let badge = roblox_api
+                .get_all_badges(experience_asset_id)
+                .await?
+                .into_iter()
+                .find(|badge| badge.id == badge_id)
+                .ok_or_else(not_found)?;
+            get_imported_badge_resources(label, badge, &experience)
+        }
+        "asset" | "assetAlias" => {
+            let asset_id = parse_roblox_id(roblox_id)?;
+            let asset = roblox_api
+                .get_all_asset_aliases(experience_asset_id)
+                .await?
+                .into_iter()
+                .find(|asset| asset.target_id == asset_id)
+                .ok_or_else(not_found)?;
+            let group_id = graph
+                .get_resource_list()
+                .into_iter()
+                .find(|resource| resource.get_id() == experience_id)
+                .and_then(|resource| match resource.get_inputs() {
+                    RobloxInputs::Experience(inputs) => inputs.group_id,
+                    _ => None,
+                });
+            let resources = get_imported_asset_resources(label, asset, group_id, &experience);
+            if resources.is_empty() {
+                return Err(format!(
+                    "Asset {} is not an image or audio asset",
+                    roblox_id
+                ));
+            }
+            resources
+        }
+        "notification" => {
+            let notification = roblox_api
+                .get_all_notifications(experience_asset_id)
+                .await?
+                .into_iter()
+                .find(|notification| notification.id == roblox_id)
+                .ok_or_else(not_found)?;
+            vec![get_imported_notification_resource(
+                label,
+                notification,
+                &experience,
+            )]
+        }
+        _ => {
+            return Err(format!(
+                "Importing {} resources is not supported. Supported types are: place, socialLink, product, pass, badge, assetAlias, notification",
+                resource_type
+            ))
+        }
+    };
+
+    let resources: Vec<RobloxResource> = match &target.label {
+        Some(label) => resources
+            .into_iter()
+            .map(|resource| resource.with_namespace(label))
+            .collect(),
+        None => resources,
+    };
+    if let Some(existing) = resources
+        .iter()
+        .find(|resource| graph.get_outputs(&resource.get_id()).is_some())
+    {
+        return Err(format!(
+            "Resource {} already exists in the environment's state",
+            existing.get_id()
+        ));
+    }
+
+    Ok(resources)
+}
+
 pub async fn save_state_to_remote(config: &RemoteStateConfig, data: &[u8]) -> Result<(), String> {
     logger::log(format!("Saving to remote object {}", Paint::cyan(config)));
 
@@ -906,15 +1285,16 @@ pub fn save_state_to_file(
     Ok(())
 }
 
+// The serialized state must be canonical: identical state should always produce byte-identical
+// files so that deployments without changes do not produce diffs in source control. Avoid adding
+// anything non-deterministic (e.g. timestamps) to the output.
 fn serialize_state(state: &ResourceStateVLatest) -> Result<Vec<u8>, String> {
-    let utc = Utc::now();
     let mut data = format!("#\n\
                                    # WARNING - Generated file. Do not modify directly unless you know \
                                      what you are doing!\n\
-                                   # This file was generated by Mantle v{} on {}\n\
+                                   # This file was generated by Mantle v{}\n\
                                    #\n\n",
-                                crate_version!(),
-                                utc.format("%FT%TZ")
+                                crate_version!()
                             ).as_bytes().to_vec();
 
     let state_data = serde_yaml::to_vec(&ResourceState::Versioned(VersionedResourceState::V6(
@@ -940,3 +1320,71 @@ pub async fn save_state(
         StateConfig::Remote(config) => save_state_to_remote(config, &data).await,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_resources() -> Vec<RobloxResource> {
+        let experience = RobloxResource::new(
+            "experience_singleton",
+            RobloxInputs::Experience(ExperienceInputs { group_id: None }),
+            &[],
+        );
+        let product = RobloxResource::new(
+            "product_gems",
+            RobloxInputs::Product(ProductInputs {
+                name: "Gems".to_owned(),
+                description: "".to_owned(),
+                price: 100,
+            }),
+            &[&experience],
+        );
+        let product_icon = RobloxResource::new(
+            "productIcon_gems",
+            RobloxInputs::ProductIcon(FileInputs {
+                file_path: "gems.png".to_owned(),
+                file_hash: "abc".to_owned(),
+            }),
+            &[&product],
+        );
+        let experience_icon = RobloxResource::new(
+            "experienceIcon_singleton",
+            RobloxInputs::ExperienceIcon(FileInputs {
+                file_path: "icon.png".to_owned(),
+                file_hash: "def".to_owned(),
+            }),
+            &[&experience],
+        );
+        vec![experience, product, product_icon, experience_icon]
+    }
+
+    fn get_state(resources: &[RobloxResource]) -> ResourceStateVLatest {
+        let graph = ResourceGraph::<RobloxResource, RobloxInputs, RobloxOutputs>::new(resources);
+        ResourceStateVLatest {
+            environments: BTreeMap::from([("production".to_owned(), graph.get_resource_list())]),
+            deployed_at: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn serialize_state_is_deterministic() {
+        let resources = get_resources();
+        let data = serialize_state(&get_state(&resources)).unwrap();
+        assert_eq!(data, serialize_state(&get_state(&resources)).unwrap());
+
+        let reversed = resources.into_iter().rev().collect::<Vec<_>>();
+        assert_eq!(data, serialize_state(&get_state(&reversed)).unwrap());
+    }
+
+    #[test]
+    fn serialize_state_round_trips() {
+        let data = serialize_state(&get_state(&get_resources())).unwrap();
+
+        let state = match parse_state("state.yml", std::str::from_utf8(&data).unwrap()).unwrap() {
+            ResourceState::Versioned(VersionedResourceState::V6(state)) => state,
+            _ => panic!("Expected the state to be parsed as the latest version"),
+        };
+        assert_eq!(data, serialize_state(&state).unwrap());
+    }
+}
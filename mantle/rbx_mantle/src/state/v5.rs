@@ -53,6 +53,9 @@ impl From<ResourceStateV5> for ResourceStateV6 {
             environments.insert(environment_name.to_owned(), environment);
         }
 
-        ResourceStateV6 { environments }
+        ResourceStateV6 {
+            environments,
+            deployed_at: BTreeMap::new(),
+        }
     }
 }
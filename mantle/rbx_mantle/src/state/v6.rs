@@ -7,4 +7,8 @@ use super::super::roblox_resource_manager::RobloxResource;
 #[derive(Serialize, Deserialize, Clone)]
 pub struct ResourceStateV6 {
     pub environments: BTreeMap<String, Vec<RobloxResource>>,
+
+    /// The time each ephemeral environment was last deployed, in seconds since the Unix epoch.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub deployed_at: BTreeMap<String, u64>,
 }
@@ -0,0 +1,271 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use super::{
+    config::{
+        AssetTargetConfig, Config, ExperienceTargetConfig, OwnerConfig, PaidAccessTargetConfig,
+        PrivateServersTargetConfig, TargetConfig,
+    },
+    project::{
+        get_environment_owner, get_environment_targets, resolve_environments, ProjectTarget,
+    },
+    state::{
+        get_desired_graph, get_social_link_type, AUDIO_ASSET_EXTENSIONS, IMAGE_ASSET_EXTENSIONS,
+    },
+};
+
+const MIN_PRODUCT_PRICE: u32 = 1;
+const MIN_PASS_PRICE: u32 = 1;
+const MIN_PAID_ACCESS_PRICE: u32 = 25;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationIssue {
+    pub environment: String,
+    pub path: String,
+    pub message: String,
+}
+
+struct Validator<'a> {
+    project_path: &'a Path,
+    environment: String,
+    issues: Vec<ValidationIssue>,
+}
+
+impl<'a> Validator<'a> {
+    fn report(&mut self, path: impl Into<String>, message: impl Into<String>) {
+        self.issues.push(ValidationIssue {
+            environment: self.environment.clone(),
+            path: path.into(),
+            message: message.into(),
+        });
+    }
+
+    fn check_file(&mut self, path: String, file: &str) {
+        if !self.project_path.join(file).is_file() {
+            self.report(path, format!("File {} does not exist", file));
+        }
+    }
+
+    fn check_asset_file(&mut self, path: String, file: &Path) {
+        match file.extension().and_then(OsStr::to_str) {
+            Some(extension)
+                if IMAGE_ASSET_EXTENSIONS.contains(&extension)
+                    || AUDIO_ASSET_EXTENSIONS.contains(&extension) => {}
+            _ => self.report(
+                path,
+                format!(
+                    "Unable to determine asset type for file {}. Supported extensions are: {}",
+                    file.display(),
+                    IMAGE_ASSET_EXTENSIONS
+                        .iter()
+                        .chain(AUDIO_ASSET_EXTENSIONS.iter())
+                        .cloned()
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            ),
+        }
+    }
+
+    fn validate_experience(&mut self, base_path: &str, experience: &ExperienceTargetConfig) {
+        match &experience.places {
+            Some(places) if places.contains_key("start") => {
+                for (label, place) in sorted(places) {
+                    if let Some(file) = &place.file {
+                        self.check_file(format!("{}.places.{}.file", base_path, label), file);
+                    }
+                }
+            }
+            _ => self.report(format!("{}.places", base_path), "No start place specified"),
+        }
+
+        if let Some(configuration) = &experience.configuration {
+            if let PaidAccessTargetConfig::Price(price) = configuration.paid_access {
+                if price < MIN_PAID_ACCESS_PRICE {
+                    self.report(
+                        format!("{}.configuration.paidAccess.price", base_path),
+                        format!(
+                            "Paid access price must be at least {} Robux",
+                            MIN_PAID_ACCESS_PRICE
+                        ),
+                    );
+                }
+
+                if !matches!(
+                    configuration.private_servers,
+                    PrivateServersTargetConfig::Disabled
+                ) {
+                    self.report(
+                        format!("{}.configuration.privateServers", base_path),
+                        "Private servers cannot be enabled when paid access is enabled",
+                    );
+                }
+            }
+        }
+
+        if let Some(icon) = &experience.icon {
+            self.check_file(format!("{}.icon", base_path), icon);
+        }
+
+        for (index, thumbnail) in experience.thumbnails.iter().flatten().enumerate() {
+            self.check_file(format!("{}.thumbnails[{}]", base_path, index), thumbnail);
+        }
+
+        for (index, social_link) in experience.social_links.iter().flatten().enumerate() {
+            let path = format!("{}.socialLinks[{}].url", base_path, index);
+            match social_link.url.domain() {
+                Some(domain) if get_social_link_type(domain).is_some() => {}
+                Some(domain) => self.report(
+                    path,
+                    format!("Unknown social link type for domain name {}", domain),
+                ),
+                None => self.report(
+                    path,
+                    format!("Unknown social link type for URL {}", social_link.url),
+                ),
+            }
+        }
+
+        for (label, product) in experience.products.iter().flat_map(sorted) {
+            let path = format!("{}.products.{}", base_path, label);
+            if product.price < MIN_PRODUCT_PRICE {
+                self.report(
+                    format!("{}.price", path),
+                    format!("Product price must be at least {} Robux", MIN_PRODUCT_PRICE),
+                );
+            }
+            if let Some(icon) = &product.icon {
+                self.check_file(format!("{}.icon", path), icon);
+            }
+        }
+
+        for (label, pass) in experience.passes.iter().flat_map(sorted) {
+            let path = format!("{}.passes.{}", base_path, label);
+            if matches!(pass.price, Some(price) if price < MIN_PASS_PRICE) {
+                self.report(
+                    format!("{}.price", path),
+                    format!("Pass price must be at least {} Robux", MIN_PASS_PRICE),
+                );
+            }
+            self.check_file(format!("{}.icon", path), &pass.icon);
+        }
+
+        for (label, badge) in experience.badges.iter().flat_map(sorted) {
+            self.check_file(format!("{}.badges.{}.icon", base_path, label), &badge.icon);
+        }
+
+        for (index, asset) in experience.assets.iter().flatten().enumerate() {
+            let path = format!("{}.assets[{}]", base_path, index);
+            match asset {
+                AssetTargetConfig::File(pattern) => {
+                    let matches = self
+                        .project_path
+                        .join(pattern)
+                        .to_str()
+                        .and_then(|pattern| glob::glob(pattern).ok())
+                        .map(|paths| {
+                            paths
+                                .filter_map(Result::ok)
+                                .filter(|path| path.is_file())
+                                .collect::<Vec<PathBuf>>()
+                        });
+                    match matches {
+                        Some(matches) if !matches.is_empty() => {
+                            for file in matches {
+                                self.check_asset_file(path.clone(), &file);
+                            }
+                        }
+                        Some(_) => self
+                            .report(path, format!("Pattern {} did not match any files", pattern)),
+                        None => self.report(path, format!("Glob pattern invalid: {}", pattern)),
+                    }
+                }
+                AssetTargetConfig::FileWithAlias { file, .. } => {
+                    self.check_file(format!("{}.file", path), file);
+                    self.check_asset_file(format!("{}.file", path), Path::new(file));
+                }
+            }
+        }
+    }
+
+    fn validate_target(&mut self, target: &ProjectTarget, owner_config: &OwnerConfig) {
+        let base_path = match &target.label {
+            Some(label) => format!("targets.{}", label),
+            None => "target".to_owned(),
+        };
+
+        let issue_count = self.issues.len();
+        match &target.config {
+            TargetConfig::Experience(experience) => {
+                self.validate_experience(&format!("{}.experience", base_path), experience)
+            }
+        }
+
+        // Building the desired graph catches any remaining problems (e.g. unreadable files). It
+        // is skipped when problems were already found since they would be reported twice.
+        if self.issues.len() == issue_count {
+            if let Err(e) = get_desired_graph(self.project_path, &[target.clone()], owner_config) {
+                self.report(base_path, e);
+            }
+        }
+    }
+}
+
+fn sorted<T>(map: &HashMap<String, T>) -> BTreeMap<&String, &T> {
+    map.iter().collect()
+}
+
+/// Validates the project's configuration for every environment without any network access.
+/// Returns all problems found.
+pub fn validate_project(project_path: &Path, config: &Config) -> Vec<ValidationIssue> {
+    let environments = match resolve_environments(&config.environments) {
+        Ok(v) => v,
+        Err(e) => {
+            return vec![ValidationIssue {
+                environment: "".to_owned(),
+                path: "environments".to_owned(),
+                message: e,
+            }]
+        }
+    };
+
+    let mut issues = Vec::new();
+    for (index, environment_config) in environments.iter().enumerate() {
+        let mut validator = Validator {
+            project_path,
+            environment: environment_config.label.clone(),
+            issues: Vec::new(),
+        };
+
+        if let Err(e) = get_environment_owner(config, environment_config) {
+            let path = match environment_config.payments {
+                Some(_) => format!("environments[{}].payments", index),
+                None => "payments".to_owned(),
+            };
+            validator.report(path, e);
+        }
+
+        let owner_config = environment_config
+            .owner
+            .clone()
+            .unwrap_or_else(|| config.owner.clone());
+        match get_environment_targets(project_path.to_path_buf(), config, environment_config) {
+            Ok(targets) => {
+                for target in targets.iter() {
+                    validator.validate_target(target, &owner_config);
+                }
+            }
+            Err(e) => validator.report(format!("environments[{}]", index), e),
+        }
+
+        issues.extend(validator.issues);
+    }
+
+    issues
+}